@@ -1,5 +1,6 @@
 
 #![no_std]
+#![allow(clippy::new_without_default)]
 use sails_rs::prelude::*;
 pub mod services;
 
@@ -14,7 +15,7 @@ impl Program {
         Self
     }
 
-    #[route("Service")]
+    #[export(route = "Service")]
     pub fn service(&self) -> Service {
         Service::new()
     }
@@ -1,15 +1,47 @@
-
-#![no_std]
 #![allow(static_mut_refs)]
 
 use sails_rs::{
     prelude::*,
-    gstd::msg,
-    collections::HashMap,
+    gstd::{exec, msg},
+    collections::{HashMap, HashSet, VecDeque},
 };
+use blake2::{digest::consts::U32, Blake2b};
+use sha3::{Digest, Keccak256};
 
 pub static mut MAIN_CONTRACT_STATE: Option<MainContractState> = None;
 
+/// Schema version of `IoMainContractState`, bump it whenever the state layout changes
+pub const STATE_VERSION: u32 = 29;
+
+/// Anti-cheat limits for submitted run stats
+const MAX_MONSTERS_DEFEATED: u32 = 1000;
+const MAX_ITEMS_PER_RUN: usize = 100;
+/// Survival time is capped at 24h, expressed in the configured `TimeUnit`
+const MAX_SURVIVAL_SECONDS: u64 = 60 * 60 * 24;
+/// Vara produces a block every 3 seconds
+const SECONDS_PER_BLOCK: u64 = 3;
+
+/// Default max keyring public key length, covers uncompressed secp256k1 keys
+const DEFAULT_MAX_KEY_LEN: u32 = 65;
+
+/// Signing context used by Substrate wallets for sr25519 signatures
+const SIGNING_CONTEXT: &[u8] = b"substrate";
+
+/// `seq` of replies that weren't emitted as events, real events start at 1
+const NO_EVENT_SEQ: u64 = 0;
+
+/// Route of the service exposed by the vNFT contracts
+const VNFT_SERVICE: &str = "Vnft";
+
+/// Max number of finished runs kept in `run_history`, the oldest ones are dropped first
+const MAX_RUN_HISTORY: usize = 1000;
+
+/// Max number of runs a single `query_runs_in_range` reply carries
+const MAX_QUERY_RUNS: usize = 100;
+
+/// Max length in bytes of a leaderboard display name
+const MAX_DISPLAY_NAME_LEN: usize = 32;
+
 /// Represents state for each user and their selected NFTs
 #[derive(Debug, Encode, Decode, TypeInfo, Clone)]
 #[codec(crate = sails_rs::scale_codec)]
@@ -29,6 +61,56 @@ pub struct RunStats {
     pub items_found: Vec<U256>,
     pub new_items_selected: Vec<U256>,
     pub survival_time: u64,
+    /// Block at which the stats were recorded, set by the contract
+    pub block_height: u32,
+    /// Who started the run, taken from the run session by the contract
+    pub initiator: ActorId,
+    /// Run the stats were submitted for, set by the contract
+    pub run_id: u64,
+}
+
+/// Stats recorded by `run_finished` with the change since the user's previous run,
+/// deltas are zero on a first run
+#[derive(Debug, Encode, Decode, TypeInfo, Clone)]
+#[codec(crate = sails_rs::scale_codec)]
+#[scale_info(crate = sails_rs::scale_info)]
+pub struct RunResult {
+    pub stats: RunStats,
+    pub monsters_defeated_delta: i64,
+    pub survival_time_delta: i64,
+}
+
+/// Everything known about a user, the key itself is left out
+#[derive(Debug, Encode, Decode, TypeInfo, Clone)]
+#[codec(crate = sails_rs::scale_codec)]
+#[scale_info(crate = sails_rs::scale_info)]
+pub struct UserProfile {
+    pub selected_nfts: Vec<(ActorId, U256)>,
+    pub last_run_stats: Option<RunStats>,
+    pub current_weapon: Option<U256>,
+    pub has_key: bool,
+    pub display_name: Option<String>,
+}
+
+/// A run started by `start_run`, with the users captured at that moment
+#[derive(Debug, Encode, Decode, TypeInfo, Clone)]
+#[codec(crate = sails_rs::scale_codec)]
+#[scale_info(crate = sails_rs::scale_info)]
+pub struct RunSession {
+    pub run_id: u64,
+    pub initiator: ActorId,
+    pub participants: Vec<ActorId>,
+}
+
+/// Metadata of a vNFT token, mirrors the vNFT contract's `TokenMetadata`
+#[derive(Debug, Encode, Decode, TypeInfo, Clone)]
+#[codec(crate = sails_rs::scale_codec)]
+#[scale_info(crate = sails_rs::scale_info)]
+pub struct TokenMetadata {
+    pub name: String,
+    pub description: String,
+    pub media: String,
+    pub reference: String,
 }
 
 /// Keyring entry for a user
@@ -41,6 +123,51 @@ pub struct KeyringEntry {
     pub metadata: Option<String>,
 }
 
+/// Hash used for the `start_run` signature
+#[derive(Debug, Encode, Decode, TypeInfo, Clone, Copy, Default, PartialEq, Eq)]
+#[codec(crate = sails_rs::scale_codec)]
+#[scale_info(crate = sails_rs::scale_info)]
+pub enum HashAlgo {
+    #[default]
+    Blake2b,
+    /// keccak256, for proofs verified on EVM chains
+    Keccak,
+}
+
+/// Method that changed a keyring entry, several of them emit the same key events
+#[derive(Debug, Encode, Decode, TypeInfo, Clone, Copy, PartialEq, Eq)]
+#[codec(crate = sails_rs::scale_codec)]
+#[scale_info(crate = sails_rs::scale_info)]
+pub enum KeyOrigin {
+    RemoveKey,
+    AdminRemoveKey,
+    UpdateKey,
+    UpdateKeyMetadata,
+    UpdateKeySigned,
+}
+
+/// Unit in which `survival_time` is submitted
+#[derive(Debug, Encode, Decode, TypeInfo, Clone, Copy, Default, PartialEq, Eq)]
+#[codec(crate = sails_rs::scale_codec)]
+#[scale_info(crate = sails_rs::scale_info)]
+pub enum TimeUnit {
+    #[default]
+    Milliseconds,
+    Seconds,
+    Blocks,
+}
+
+impl TimeUnit {
+    /// Highest survival time accepted, 24h in this unit
+    pub fn max_survival_time(self) -> u64 {
+        match self {
+            TimeUnit::Milliseconds => MAX_SURVIVAL_SECONDS * 1000,
+            TimeUnit::Seconds => MAX_SURVIVAL_SECONDS,
+            TimeUnit::Blocks => MAX_SURVIVAL_SECONDS / SECONDS_PER_BLOCK,
+        }
+    }
+}
+
 /// State for MainContract
 #[derive(Debug, Clone, Default)]
 pub struct MainContractState {
@@ -48,6 +175,55 @@ pub struct MainContractState {
     pub user_nft_selections: HashMap<ActorId, Vec<(ActorId, U256)>>,
     pub last_run_stats: HashMap<ActorId, RunStats>,
     pub keyring: HashMap<ActorId, KeyringEntry>,
+    pub current_weapon: HashMap<ActorId, U256>,
+    pub hash_algo: HashAlgo,
+    /// NFT contracts users may select from, empty means any contract is allowed
+    pub allowed_nft_contracts: Vec<ActorId>,
+    pub next_run_id: u64,
+    pub active_runs: HashMap<u64, RunSession>,
+    pub runs_finished: u64,
+    pub survival_time_unit: TimeUnit,
+    /// Sequence number of the last emitted event
+    pub event_seq: u64,
+    /// Highest item id accepted in run results
+    pub max_item_id: U256,
+    /// Block height of each user's latest selection, weapon change or finished run
+    pub last_active_block: HashMap<ActorId, u32>,
+    /// Blocks a user must wait between selections, zero disables the cooldown
+    pub selection_cooldown_blocks: u32,
+    pub last_selection_block: HashMap<ActorId, u32>,
+    /// Who added each admin, the seed admin maps to itself
+    pub admin_added_by: HashMap<ActorId, ActorId>,
+    /// Proposed sole admin and the admin who proposed it, awaiting acceptance
+    pub pending_admin: Option<(ActorId, ActorId)>,
+    /// Ids of every run each user started, in start order
+    pub runs_initiated: HashMap<ActorId, Vec<u64>>,
+    /// Ids of every run each user was a participant of, in start order
+    pub runs_joined: HashMap<ActorId, Vec<u64>>,
+    pub display_names: HashMap<ActorId, String>,
+    /// Events are skipped while `false`, state changes and replies are unaffected
+    pub emit_events: bool,
+    /// (run_id, user) pairs whose results were already submitted through `finish_run` or
+    /// `run_finished`, dropped along with the run once every participant has submitted
+    pub finished_runs: HashSet<(u64, ActorId)>,
+    /// Accounts each user allowed to set their selection, e.g. a guild manager
+    pub delegates: HashMap<ActorId, Vec<ActorId>>,
+    /// Most participants a run may have, zero means no cap
+    pub max_participants: u32,
+    /// vNFT contract weapons must be owned on, `None` skips the check for off-chain weapons
+    pub weapon_contract: Option<ActorId>,
+    /// Every recorded run in submission order, so block heights never decrease
+    /// Left out of `query_state`, page through it with `query_runs_in_range`
+    pub run_history: VecDeque<RunStats>,
+    /// Longest public key accepted in the keyring, in bytes
+    pub max_key_len: u32,
+    /// vNFT contract `finish_run` mints `new_items_selected` on, `None` disables minting
+    pub reward_contract: Option<ActorId>,
+    /// Prefix of reward token media, followed by the item id, e.g. `ipfs://<cid>/`
+    pub reward_media_base: String,
+    /// Reward tokens minted for each (run_id, user), dropped along with the run's history entry
+    /// An empty list marks a mint that is still waiting for the vNFT reply
+    pub awarded_rewards: HashMap<(u64, ActorId), Vec<U256>>,
 }
 
 impl MainContractState {
@@ -55,6 +231,10 @@ impl MainContractState {
         unsafe {
             MAIN_CONTRACT_STATE = Some(Self {
                 admins: vec![msg::source()],
+                admin_added_by: HashMap::from([(msg::source(), msg::source())]),
+                max_item_id: U256::MAX,
+                emit_events: true,
+                max_key_len: DEFAULT_MAX_KEY_LEN,
                 ..Default::default()
             });
         }
@@ -72,19 +252,29 @@ impl MainContractState {
 }
 
 /// Events for MainContract
-#[derive(Debug, Encode, Decode, TypeInfo)]
+/// Every event carries the contract's event sequence number as its last field
+/// (`RunStarted` has `signature_bytes` appended after it), so indexers can detect
+/// dropped or out-of-order events
+/// Replies of no-op calls, which emit nothing, carry seq 0
+#[derive(Debug, Encode, Decode, TypeInfo, Clone)]
 #[codec(crate = sails_rs::scale_codec)]
 #[scale_info(crate = sails_rs::scale_info)]
 pub enum MainEvent {
     NFTsSelected {
         user: ActorId,
         selected_nfts: Vec<(ActorId, U256)>,
+        seq: u64,
     },
-    AdminAdded(ActorId),
+    AdminAdded(ActorId, u64),
     RunStarted {
         initiator: ActorId,
         participants: Vec<(ActorId, Vec<(ActorId, U256)>)>,
         signature: [u8; 32],
+        run_id: u64,
+        seq: u64,
+        /// Same bytes as `signature`, variable length so later schemes can fit
+        /// Kept after `seq` so decoders of the earlier layout still read the prefix
+        signature_bytes: Vec<u8>,
     },
     RunFinished {
         user: ActorId,
@@ -92,24 +282,146 @@ pub enum MainEvent {
         items_found: Vec<U256>,
         new_items_selected: Vec<U256>,
         survival_time: u64,
+        initiator: ActorId,
+        /// Reward tokens minted for `new_items_selected`, empty while minting is disabled
+        awarded_token_ids: Vec<U256>,
+        seq: u64,
     },
     NewWeaponSelected {
         user: ActorId,
         token_id: U256,
+        seq: u64,
     },
     KeyAdded {
         user: ActorId,
         public_key: Vec<u8>,
         metadata: Option<String>,
+        seq: u64,
     },
     KeyRemoved {
         user: ActorId,
+        origin: KeyOrigin,
+        seq: u64,
     },
     KeyUpdated {
         user: ActorId,
         public_key: Vec<u8>,
         metadata: Option<String>,
+        origin: KeyOrigin,
+        seq: u64,
+    },
+    UserReset(ActorId, u64),
+    UnauthorizedAttempt {
+        caller: ActorId,
+        action: String,
+        seq: u64,
+    },
+    HashAlgoSet(HashAlgo, u64),
+    AllowedContractAdded(ActorId, u64),
+    AllowedContractRemoved(ActorId, u64),
+    SurvivalTimeUnitSet(TimeUnit, u64),
+    MaxItemIdSet(U256, u64),
+    SelectionCooldownSet(u32, u64),
+    MaxParticipantsSet(u32, u64),
+    WeaponContractSet(Option<ActorId>, u64),
+    MaxKeyLenSet(u32, u64),
+    RewardContractSet(Option<ActorId>, u64),
+    RewardMediaBaseSet(String, u64),
+    RunAborted {
+        run_id: u64,
+        by: ActorId,
+        seq: u64,
+    },
+    DisplayNameSet {
+        user: ActorId,
+        name: String,
+        seq: u64,
+    },
+    NFTsSelectedFor {
+        user: ActorId,
+        delegate: ActorId,
+        selected_nfts: Vec<(ActorId, U256)>,
+        seq: u64,
+    },
+    DelegateAuthorized {
+        user: ActorId,
+        delegate: ActorId,
+        seq: u64,
+    },
+    DelegateRevoked {
+        user: ActorId,
+        delegate: ActorId,
+        seq: u64,
+    },
+    SelectionCancelled {
+        user: ActorId,
+        seq: u64,
+    },
+    AdminTransferProposed {
+        new_admin: ActorId,
+        by: ActorId,
+        seq: u64,
+    },
+    AdminRoleTransferred {
+        new_admin: ActorId,
+        seq: u64,
     },
+    RunStatsPruned {
+        removed: u32,
+        seq: u64,
+    },
+    /// `finish_run` recorded the run but couldn't mint its rewards, admins can retry
+    /// with `admin_award_items`
+    RewardAwardFailed {
+        user: ActorId,
+        run_id: u64,
+        error: MainError,
+        seq: u64,
+    },
+    EmitEventsSet(bool, u64),
+}
+
+/// Aggregate numbers for dashboards
+#[derive(Debug, Encode, Decode, TypeInfo, Clone, PartialEq, Eq)]
+#[codec(crate = sails_rs::scale_codec)]
+#[scale_info(crate = sails_rs::scale_info)]
+pub struct GameStats {
+    pub users_with_selections: u32,
+    pub admins: u32,
+    pub runs_finished: u64,
+    pub nfts_selected: u32,
+}
+
+/// Errors for MainContract
+#[derive(Debug, Encode, Decode, TypeInfo, Clone, PartialEq, Eq)]
+#[codec(crate = sails_rs::scale_codec)]
+#[scale_info(crate = sails_rs::scale_info)]
+pub enum MainError {
+    TooManyMonstersDefeated,
+    TooManyItemsFound,
+    TooManyNewItemsSelected,
+    SurvivalTimeTooHigh,
+    Overflow,
+    Unauthorized,
+    RunNotFound,
+    NotAParticipant,
+    NoKey,
+    InvalidSignature,
+    NoActiveRun,
+    ItemIdOutOfRange,
+    EmptySelection,
+    DuplicateSelection,
+    ContractNotAllowed,
+    SelectionCooldown,
+    InvalidDisplayName,
+    RunAlreadyFinished,
+    TooManyParticipants,
+    NotWeaponOwner,
+    InvalidBlockRange,
+    InvalidKey,
+    VnftCallFailed,
+    AlreadyAwarded,
+    NoSelection,
 }
 
 /// Queryable IoState
@@ -121,10 +433,37 @@ pub struct IoMainContractState {
     pub user_nft_selections: Vec<UserSelection>,
     pub last_run_stats: Vec<RunStats>,
     pub keyring: Vec<KeyringEntry>,
+    pub current_weapon: Vec<(ActorId, U256)>,
+    pub hash_algo: HashAlgo,
+    pub allowed_nft_contracts: Vec<ActorId>,
+    pub next_run_id: u64,
+    pub active_runs: Vec<RunSession>,
+    pub runs_finished: u64,
+    pub survival_time_unit: TimeUnit,
+    pub event_seq: u64,
+    pub max_item_id: U256,
+    pub last_active_block: Vec<(ActorId, u32)>,
+    pub selection_cooldown_blocks: u32,
+    pub last_selection_block: Vec<(ActorId, u32)>,
+    pub admin_added_by: Vec<(ActorId, ActorId)>,
+    pub pending_admin: Option<(ActorId, ActorId)>,
+    pub runs_initiated: Vec<(ActorId, Vec<u64>)>,
+    pub runs_joined: Vec<(ActorId, Vec<u64>)>,
+    pub display_names: Vec<(ActorId, String)>,
+    pub emit_events: bool,
+    pub finished_runs: Vec<(u64, ActorId)>,
+    pub delegates: Vec<(ActorId, Vec<ActorId>)>,
+    pub max_participants: u32,
+    pub weapon_contract: Option<ActorId>,
+    pub max_key_len: u32,
+    pub reward_contract: Option<ActorId>,
+    pub reward_media_base: String,
+    pub awarded_rewards: Vec<((u64, ActorId), Vec<U256>)>,
+    pub state_version: u32,
 }
 
-impl From<MainContractState> for IoMainContractState {
-    fn from(state: MainContractState) -> Self {
+impl From<&MainContractState> for IoMainContractState {
+    fn from(state: &MainContractState) -> Self {
         let user_nft_selections = state.user_nft_selections
             .iter()
             .map(|(k, v)| UserSelection {
@@ -140,15 +479,133 @@ impl From<MainContractState> for IoMainContractState {
             .values()
             .cloned()
             .collect();
+        let active_runs = state.active_runs
+            .values()
+            .cloned()
+            .collect();
+        let current_weapon = state.current_weapon
+            .iter()
+            .map(|(k, v)| (*k, *v))
+            .collect();
+        let last_active_block = state.last_active_block
+            .iter()
+            .map(|(k, v)| (*k, *v))
+            .collect();
+        let last_selection_block = state.last_selection_block
+            .iter()
+            .map(|(k, v)| (*k, *v))
+            .collect();
+        let admin_added_by = state.admin_added_by
+            .iter()
+            .map(|(k, v)| (*k, *v))
+            .collect();
         IoMainContractState {
-            admins: state.admins,
+            admins: state.admins.clone(),
             user_nft_selections,
             last_run_stats,
             keyring,
+            current_weapon,
+            hash_algo: state.hash_algo,
+            allowed_nft_contracts: state.allowed_nft_contracts.clone(),
+            next_run_id: state.next_run_id,
+            active_runs,
+            runs_finished: state.runs_finished,
+            survival_time_unit: state.survival_time_unit,
+            event_seq: state.event_seq,
+            max_item_id: state.max_item_id,
+            last_active_block,
+            selection_cooldown_blocks: state.selection_cooldown_blocks,
+            last_selection_block,
+            admin_added_by,
+            pending_admin: state.pending_admin,
+            runs_initiated: state.runs_initiated
+                .iter()
+                .map(|(k, v)| (*k, v.clone()))
+                .collect(),
+            runs_joined: state.runs_joined
+                .iter()
+                .map(|(k, v)| (*k, v.clone()))
+                .collect(),
+            display_names: state.display_names
+                .iter()
+                .map(|(k, v)| (*k, v.clone()))
+                .collect(),
+            emit_events: state.emit_events,
+            finished_runs: state.finished_runs.iter().copied().collect(),
+            delegates: state.delegates
+                .iter()
+                .map(|(k, v)| (*k, v.clone()))
+                .collect(),
+            max_participants: state.max_participants,
+            weapon_contract: state.weapon_contract,
+            max_key_len: state.max_key_len,
+            reward_contract: state.reward_contract,
+            reward_media_base: state.reward_media_base.clone(),
+            awarded_rewards: state.awarded_rewards
+                .iter()
+                .map(|(k, v)| (*k, v.clone()))
+                .collect(),
+            state_version: STATE_VERSION,
+        }
+    }
+}
+
+/// Number of keys in each map of `MainContractState`, lets backups detect
+/// a map that was exported only partially
+#[derive(Debug, Encode, Decode, TypeInfo, Clone, PartialEq, Eq)]
+#[codec(crate = sails_rs::scale_codec)]
+#[scale_info(crate = sails_rs::scale_info)]
+pub struct MapKeyCounts {
+    pub user_nft_selections: u32,
+    pub last_run_stats: u32,
+    pub keyring: u32,
+    pub current_weapon: u32,
+    pub active_runs: u32,
+    pub last_active_block: u32,
+    pub last_selection_block: u32,
+    pub admin_added_by: u32,
+    pub runs_initiated: u32,
+    pub runs_joined: u32,
+    pub display_names: u32,
+    pub finished_runs: u32,
+    pub delegates: u32,
+    pub awarded_rewards: u32,
+    pub run_history: u32,
+}
+
+impl From<&MainContractState> for MapKeyCounts {
+    fn from(state: &MainContractState) -> Self {
+        MapKeyCounts {
+            user_nft_selections: state.user_nft_selections.len() as u32,
+            last_run_stats: state.last_run_stats.len() as u32,
+            keyring: state.keyring.len() as u32,
+            current_weapon: state.current_weapon.len() as u32,
+            active_runs: state.active_runs.len() as u32,
+            last_active_block: state.last_active_block.len() as u32,
+            last_selection_block: state.last_selection_block.len() as u32,
+            admin_added_by: state.admin_added_by.len() as u32,
+            runs_initiated: state.runs_initiated.len() as u32,
+            runs_joined: state.runs_joined.len() as u32,
+            display_names: state.display_names.len() as u32,
+            finished_runs: state.finished_runs.len() as u32,
+            delegates: state.delegates.len() as u32,
+            awarded_rewards: state.awarded_rewards.len() as u32,
+            run_history: state.run_history.len() as u32,
         }
     }
 }
 
+/// Off-chain backup of the whole state
+#[derive(Debug, Encode, Decode, TypeInfo)]
+#[codec(crate = sails_rs::scale_codec)]
+#[scale_info(crate = sails_rs::scale_info)]
+pub struct FullSnapshot {
+    pub state: IoMainContractState,
+    /// Recorded runs oldest first, which `state` leaves out
+    pub run_history: Vec<RunStats>,
+    pub map_key_counts: MapKeyCounts,
+}
+
 #[derive(Default)]
 pub struct Service;
 
@@ -157,225 +614,1202 @@ impl Service {
     pub fn seed() {
         MainContractState::init();
     }
-}
-
-#[sails_rs::service(events = MainEvent)]
-impl Service {
-    pub fn new() -> Self { Self }
 
-    /// User selects which NFTs wants to use
-    /// Each NFT selection is a tuple: (nft_contract_id, token_id)
-    pub fn nfts_selected_by_user(&mut self, selected_nfts: Vec<(ActorId, U256)>) -> MainEvent {
-        let mut state = MainContractState::state_mut();
-        let user = msg::source();
-
-        // Validation: no duplicates, no empty
-        if selected_nfts.is_empty() {
-            panic!("NFT selection can't be empty");
-        }
-        // Optionally: only unique NFTs
-        let mut seen: Vec<(ActorId, U256)> = Vec::with_capacity(selected_nfts.len());
-        for (cid, tid) in &selected_nfts {
-            if seen.contains(&(*cid, *tid)) {
-                panic!("Duplicate NFT selection");
-            }
-            seen.push((*cid, *tid));
+    /// Simple anti-cheat: stats must be in reasonable ranges
+    fn check_run_stats(
+        monsters_defeated: u32,
+        items_found_len: usize,
+        new_items_len: usize,
+        survival_time: u64,
+    ) -> Result<(), MainError> {
+        if monsters_defeated > MAX_MONSTERS_DEFEATED {
+            return Err(MainError::TooManyMonstersDefeated);
         }
-        // Store the selection for the user, overwrite previous selection
-        state.user_nft_selections.insert(user, selected_nfts.clone());
-
-        self.emit_event(MainEvent::NFTsSelected {
-            user,
-            selected_nfts
-        }).expect("Failed to emit event");
-
-        MainEvent::NFTsSelected {
-            user,
-            selected_nfts,
+        if items_found_len > MAX_ITEMS_PER_RUN {
+            return Err(MainError::TooManyItemsFound);
         }
-    }
-
-    /// Adds an admin (only admins can do this)
-    pub fn add_admin(&mut self, new_admin: ActorId) -> MainEvent {
-        let mut state = MainContractState::state_mut();
-        let caller = msg::source();
-        if !state.admins.contains(&caller) {
-            panic!("Only admins can add new admins");
+        if new_items_len > MAX_ITEMS_PER_RUN {
+            return Err(MainError::TooManyNewItemsSelected);
         }
-        if !state.admins.contains(&new_admin) {
-            state.admins.push(new_admin);
+        let unit = MainContractState::state_ref().survival_time_unit;
+        if survival_time > unit.max_survival_time() {
+            return Err(MainError::SurvivalTimeTooHigh);
         }
-        self.emit_event(MainEvent::AdminAdded(new_admin)).expect("Failed to emit event");
-        MainEvent::AdminAdded(new_admin)
-    }
-
-    /// Query: gets NFTs selected by a user (returns only the token IDs)
-    pub fn query_user_nfts(&self, user: ActorId) -> Option<Vec<U256>> {
-        MainContractState::state_ref()
-            .user_nft_selections
-            .get(&user)
-            .map(|nfts| nfts.iter().map(|(_cid, tid)| *tid).collect())
-    }
-
-    /// Query: get all users which have selected NFTs and their selections
-    pub fn query_all_user_selections(&self) -> Vec<UserSelection> {
-        MainContractState::state_ref()
-            .user_nft_selections
-            .iter()
-            .map(|(k, v)| UserSelection {
-                user: *k,
-                selected_nfts: v.clone(),
-            })
-            .collect()
-    }
-
-    /// Query: returns all admins
-    pub fn query_admins(&self) -> Vec<ActorId> {
-        MainContractState::state_ref().admins.clone()
-    }
-
-    /// Query: returns full contract state
-    pub fn query_state(&self) -> IoMainContractState {
-        MainContractState::state_ref().clone().into()
+        Ok(())
     }
 
-    /// Starts a run/game session and signs the creation so the player can start
-    pub fn start_run(&mut self) -> MainEvent {
-        let state = MainContractState::state_ref();
-        let initiator = msg::source();
-
-        // Gather all participants and their NFT selections
-        let participants: Vec<(ActorId, Vec<(ActorId, U256)>)> = state
-            .user_nft_selections
-            .iter()
-            .map(|(user, nfts)| (*user, nfts.clone()))
-            .collect();
-
-        if participants.is_empty() {
-            panic!("No users have selected NFTs. Cannot start run.");
-        }
-
-        // Create a hash/signature of the run creation for proof
+    /// Hashes the run creation so it can be used as proof
+    fn run_signature(
+        hash_algo: HashAlgo,
+        initiator: ActorId,
+        participants: &[(ActorId, Vec<(ActorId, U256)>)],
+    ) -> [u8; 32] {
         let mut hash_data = Vec::new();
         hash_data.extend_from_slice(&initiator.encode());
-        for (user, nfts) in &participants {
+        for (user, nfts) in participants {
             hash_data.extend_from_slice(&user.encode());
             for (cid, tid) in nfts {
                 hash_data.extend_from_slice(&cid.encode());
                 hash_data.extend_from_slice(&tid.encode());
             }
         }
-        let signature = sails_rs::prelude::hash::blake2b_256(&hash_data);
+        match hash_algo {
+            HashAlgo::Blake2b => Blake2b::<U32>::digest(&hash_data).into(),
+            HashAlgo::Keccak => Keccak256::digest(&hash_data).into(),
+        }
+    }
+
+    /// Signs and records a run for the given participants
+    fn begin_run(&mut self, participants: Vec<(ActorId, Vec<(ActorId, U256)>)>) -> MainEvent {
+        let state = MainContractState::state_mut();
+        let initiator = msg::source();
+
+        // Keep the RunStarted event within the reply size limit
+        if state.max_participants != 0 && participants.len() > state.max_participants as usize {
+            panic!("{:?}", MainError::TooManyParticipants);
+        }
+
+        // Create a hash/signature of the run creation for proof
+        let signature = Self::run_signature(state.hash_algo, initiator, &participants);
 
-        self.emit_event(MainEvent::RunStarted {
+        // Remember who was part of the run so only they can finish it
+        let run_id = state.next_run_id;
+        state.next_run_id = Self::checked_increment(run_id);
+        state.active_runs.insert(run_id, RunSession {
+            run_id,
             initiator,
-            participants: participants.clone(),
-            signature,
-        }).expect("Failed to emit event");
+            participants: participants.iter().map(|(user, _)| *user).collect(),
+        });
+        state.runs_initiated.entry(initiator).or_default().push(run_id);
+        for (user, _) in &participants {
+            state.runs_joined.entry(*user).or_default().push(run_id);
+        }
 
-        MainEvent::RunStarted {
+        self.notify(MainEvent::RunStarted {
             initiator,
             participants,
             signature,
+            run_id,
+            seq: Self::next_event_seq(),
+            signature_bytes: signature.to_vec(),
+        })
+    }
+
+    /// Keys must be non-empty and no longer than the configured `max_key_len`
+    fn check_public_key(public_key: &[u8]) -> Result<(), MainError> {
+        let max_key_len = MainContractState::state_ref().max_key_len as usize;
+        if public_key.is_empty() || public_key.len() > max_key_len {
+            return Err(MainError::InvalidKey);
         }
+        Ok(())
     }
 
-    /// Called when a run finishes to submit stats
-    pub fn finish_run(
-        &mut self,
-        monsters_defeated: u32,
-        items_found: Vec<U256>,
-        new_items_selected: Vec<U256>,
-        survival_time: u64,
-    ) -> MainEvent {
-        let mut state = MainContractState::state_mut();
-        let user = msg::source();
+    /// Checks an sr25519 signature of `message`. polkadot.js `signRaw` signs the
+    /// message wrapped in `<Bytes>..</Bytes>`, so that form is accepted as well
+    fn verify_sr25519(public_key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+        let (Ok(public_key), Ok(signature)) = (
+            schnorrkel::PublicKey::from_bytes(public_key),
+            schnorrkel::Signature::from_bytes(signature),
+        ) else {
+            return false;
+        };
+        if public_key
+            .verify_simple(SIGNING_CONTEXT, message, &signature)
+            .is_ok()
+        {
+            return true;
+        }
+        let wrapped = [b"<Bytes>".as_slice(), message, b"</Bytes>".as_slice()].concat();
+        public_key
+            .verify_simple(SIGNING_CONTEXT, &wrapped, &signature)
+            .is_ok()
+    }
+
+    /// Validates and stores the user's selection, overwriting any previous one
+    fn store_selection(user: ActorId, selected_nfts: &[(ActorId, U256)]) {
+        if let Err(err) = Self::check_selection(user, selected_nfts) {
+            panic!("{:?}", err);
+        }
+        let state = MainContractState::state_mut();
 
-        // Simple anti-cheat: stats must be in reasonable ranges
-        if monsters_defeated > 1000 {
-            panic!("Invalid stats: too many monsters defeated");
+        state.user_nft_selections.insert(user, selected_nfts.to_vec());
+        state.last_selection_block.insert(user, exec::block_height());
+        Self::mark_active(state, user);
+    }
+
+    /// Validation: cooldown elapsed, no empty, no duplicates, only allowed contracts (if any are set)
+    fn check_selection(user: ActorId, selected_nfts: &[(ActorId, U256)]) -> Result<(), MainError> {
+        let state = MainContractState::state_ref();
+        if let Some(last) = state.last_selection_block.get(&user) {
+            if exec::block_height() < last.saturating_add(state.selection_cooldown_blocks) {
+                return Err(MainError::SelectionCooldown);
+            }
         }
-        if items_found.len() > 100 {
-            panic!("Invalid stats: too many items found");
+        if selected_nfts.is_empty() {
+            return Err(MainError::EmptySelection);
         }
-        if new_items_selected.len() > 100 {
-            panic!("Invalid stats: too many new items selected");
+        let mut seen: Vec<(ActorId, U256)> = Vec::with_capacity(selected_nfts.len());
+        for (cid, tid) in selected_nfts {
+            if seen.contains(&(*cid, *tid)) {
+                return Err(MainError::DuplicateSelection);
+            }
+            seen.push((*cid, *tid));
         }
-        if survival_time > 1000 * 60 * 60 * 24 {
-            panic!("Invalid stats: survival time too high");
+        let allowed = &state.allowed_nft_contracts;
+        if !allowed.is_empty() && selected_nfts.iter().any(|(cid, _)| !allowed.contains(cid)) {
+            return Err(MainError::ContractNotAllowed);
         }
+        Ok(())
+    }
 
-        let stats = RunStats {
-            user,
-            monsters_defeated,
-            items_found: items_found.clone(),
-            new_items_selected: new_items_selected.clone(),
-            survival_time,
-        };
-        state.last_run_stats.insert(user, stats.clone());
-
-        self.emit_event(MainEvent::RunFinished {
-            user,
-            monsters_defeated,
-            items_found,
-            new_items_selected,
-            survival_time,
-        }).expect("Failed to emit event");
-
-        MainEvent::RunFinished {
-            user,
-            monsters_defeated,
-            items_found,
-            new_items_selected,
-            survival_time,
+    /// Rejects item ids above the configured `max_item_id`
+    fn validate_item_ids(items_found: &[U256], new_items_selected: &[U256]) -> Result<(), MainError> {
+        let max_item_id = MainContractState::state_ref().max_item_id;
+        if items_found
+            .iter()
+            .chain(new_items_selected)
+            .any(|item_id| *item_id > max_item_id)
+        {
+            return Err(MainError::ItemIdOutOfRange);
         }
+        Ok(())
     }
 
-    /// Sets a new selected weapon for the user
-    pub fn set_new_selected_weapon(&mut self, token_id: U256) -> MainEvent {
-        let user = msg::source();
+    /// Stores the user's latest stats and counts the finished run
+    fn record_run_stats(state: &mut MainContractState, user: ActorId, stats: RunStats) {
+        state.runs_finished = Self::checked_increment(state.runs_finished);
+        if state.run_history.len() >= MAX_RUN_HISTORY {
+            if let Some(oldest) = state.run_history.pop_front() {
+                state.awarded_rewards.remove(&(oldest.run_id, oldest.user));
+            }
+        }
+        state.run_history.push_back(stats.clone());
+        state.last_run_stats.insert(user, stats);
+        Self::mark_active(state, user);
+    }
 
-        self.emit_event(MainEvent::NewWeaponSelected {
-            user,
-            token_id,
-        }).expect("Failed to emit event");
+    /// Drops a run's session and its `finished_runs` entries once nobody can submit to it
+    fn close_run(state: &mut MainContractState, run_id: u64) {
+        if let Some(run) = state.active_runs.remove(&run_id) {
+            for participant in run.participants {
+                state.finished_runs.remove(&(run_id, participant));
+            }
+        }
+    }
 
-        MainEvent::NewWeaponSelected {
-            user,
-            token_id,
+    /// Error for a run id without an open session, ids below `next_run_id` belong to
+    /// runs that were already finished or aborted
+    fn missing_run_error(state: &MainContractState, run_id: u64) -> MainError {
+        if run_id < state.next_run_id {
+            MainError::RunAlreadyFinished
+        } else {
+            MainError::RunNotFound
         }
     }
 
-    /// Returns the new stats to the user after the run finishes
-    pub fn run_finished(&mut self, new_status: RunStats) -> RunStats {
-        // Anti-cheat validation
-        if new_status.monsters_defeated > 1000 {
-            panic!("Invalid stats: too many monsters defeated");
+    /// Checks `user` may submit results for `run_id` and marks them as submitted, closing
+    /// the run once every participant has submitted, returns the run's initiator
+    /// Shared by `finish_run` and `run_finished` so a run takes one result per participant
+    fn claim_run_result(state: &mut MainContractState, run_id: u64, user: ActorId) -> ActorId {
+        // Results must be tied to actual gameplay
+        if !state.user_nft_selections.contains_key(&user) {
+            panic!("{:?}", MainError::NoActiveRun);
         }
-        if new_status.items_found.len() > 100 {
-            panic!("Invalid stats: too many items found");
+        let Some(run) = state.active_runs.get(&run_id) else {
+            panic!("{:?}", Self::missing_run_error(state, run_id));
+        };
+        if !run.participants.contains(&user) {
+            panic!("{:?}", MainError::NotAParticipant);
         }
-        if new_status.new_items_selected.len() > 100 {
-            panic!("Invalid stats: too many new items selected");
+        let initiator = run.initiator;
+        // A retried submission must not overwrite the first result
+        if !state.finished_runs.insert((run_id, user)) {
+            panic!("{:?}", MainError::RunAlreadyFinished);
         }
-        if new_status.survival_time > 1000 * 60 * 60 * 24 {
-            panic!("Invalid stats: survival time too high");
+        if run
+            .participants
+            .iter()
+            .all(|participant| state.finished_runs.contains(&(run_id, *participant)))
+        {
+            Self::close_run(state, run_id);
         }
+        initiator
+    }
 
-        let mut state = MainContractState::state_mut();
-        let user = msg::source();
-
-        // Store the new stats for the user
-        state.last_run_stats.insert(user, new_status.clone());
+    /// Records the current block as the user's latest activity
+    fn mark_active(state: &mut MainContractState, user: ActorId) {
+        state.last_active_block.insert(user, exec::block_height());
+    }
 
-        // Return the new stats to the user
-        new_status
+    /// Calls a vNFT contract method and decodes the value from its reply
+    async fn vnft_call<T: Decode>(contract: ActorId, method: &str, args: impl Encode) -> T {
+        match Self::try_vnft_call(contract, method, args).await {
+            Ok(value) => value,
+            Err(err) => panic!("{:?}", err),
+        }
     }
 
-    /// Keyring: Add a public key for the user
-    pub fn add_key(&mut self, public_key: Vec<u8>, metadata: Option<String>) -> MainEvent {
-        let mut state = MainContractState::state_mut();
-        let user = msg::source();
+    /// Like `vnft_call`, but a failed send, an error reply or an undecodable reply
+    /// is returned as `VnftCallFailed` instead of panicking
+    async fn try_vnft_call<T: Decode>(
+        contract: ActorId,
+        method: &str,
+        args: impl Encode,
+    ) -> Result<T, MainError> {
+        let request = [VNFT_SERVICE.encode(), method.encode(), args.encode()].concat();
+        let reply = msg::send_bytes_for_reply(contract, request, 0, 0)
+            .map_err(|_| MainError::VnftCallFailed)?
+            .await
+            .map_err(|_| MainError::VnftCallFailed)?;
+        let (_service, _method, value) = <(String, String, T)>::decode(&mut reply.as_slice())
+            .map_err(|_| MainError::VnftCallFailed)?;
+        Ok(value)
+    }
+
+    /// Mints one reward token per item id to `user` with a single `MintBatch` call,
+    /// returning the new token ids in item order
+    async fn mint_item_rewards(
+        vnft_contract: ActorId,
+        user: ActorId,
+        item_ids: &[U256],
+    ) -> Result<Vec<U256>, MainError> {
+        let rewards: Vec<TokenMetadata> = item_ids
+            .iter()
+            .map(|item_id| TokenMetadata {
+                name: format!("Item #{item_id}"),
+                description: String::new(),
+                media: format!("{}{item_id}", MainContractState::state_ref().reward_media_base),
+                reference: String::new(),
+            })
+            .collect();
+        let token_ids: Vec<U256> =
+            Self::try_vnft_call(vnft_contract, "MintBatch", (user, rewards)).await?;
+        if token_ids.len() != item_ids.len() {
+            return Err(MainError::VnftCallFailed);
+        }
+        Ok(token_ids)
+    }
+
+    /// Mints the `new_items_selected` of `user`'s stats for `run_id` on `reward_contract`
+    /// unless they were already awarded, recording the minted token ids
+    /// The award is marked before the vNFT call so a concurrent award can't mint twice,
+    /// and unmarked if the call fails so it can be retried
+    async fn award_run(
+        reward_contract: ActorId,
+        run_id: u64,
+        user: ActorId,
+        item_ids: Vec<U256>,
+    ) -> Result<Vec<U256>, MainError> {
+        if item_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        let key = (run_id, user);
+        let state = MainContractState::state_mut();
+        if state.awarded_rewards.contains_key(&key) {
+            return Err(MainError::AlreadyAwarded);
+        }
+        state.awarded_rewards.insert(key, Vec::new());
+
+        let minted = Self::mint_item_rewards(reward_contract, user, &item_ids).await;
+        let awarded_rewards = &mut MainContractState::state_mut().awarded_rewards;
+        // The marker is gone if the run left the history while the mint was pending
+        let pending = awarded_rewards.get(&key).is_some_and(Vec::is_empty);
+        match minted {
+            Ok(token_ids) => {
+                if pending {
+                    awarded_rewards.insert(key, token_ids.clone());
+                }
+                Ok(token_ids)
+            }
+            Err(err) => {
+                if pending {
+                    awarded_rewards.remove(&key);
+                }
+                Err(err)
+            }
+        }
+    }
+
+    /// Adds one to a counter, panicking with `Overflow` instead of wrapping around
+    fn checked_increment(counter: u64) -> u64 {
+        counter
+            .checked_add(1)
+            .unwrap_or_else(|| panic!("{:?}", MainError::Overflow))
+    }
+
+    /// Change between two survival times, `Overflow` if it doesn't fit the `i64` delta
+    /// instead of wrapping like an `as i64` cast would
+    fn survival_time_delta(new: u64, previous: u64) -> Result<i64, MainError> {
+        i64::try_from(i128::from(new) - i128::from(previous)).map_err(|_| MainError::Overflow)
+    }
+
+    /// Bumps and returns the event sequence number
+    fn next_event_seq() -> u64 {
+        let state = MainContractState::state_mut();
+        state.event_seq = Self::checked_increment(state.event_seq);
+        state.event_seq
+    }
+
+    /// Emits the event (unless emission is turned off) and hands it back as the reply
+    fn notify(&mut self, event: MainEvent) -> MainEvent {
+        if MainContractState::state_ref().emit_events {
+            self.emit_event(event.clone()).expect("Failed to emit event");
+        }
+        event
+    }
+
+    /// Panics if the caller is not an admin
+    fn ensure_is_admin() {
+        if !MainContractState::state_ref().admins.contains(&msg::source()) {
+            panic!("Only admins can perform this action");
+        }
+    }
+}
+
+#[sails_rs::service(events = MainEvent)]
+impl Service {
+    pub fn new() -> Self { Self }
+
+    /// User selects which NFTs wants to use
+    /// Each NFT selection is a tuple: (nft_contract_id, token_id)
+    pub fn nfts_selected_by_user(&mut self, selected_nfts: Vec<(ActorId, U256)>) -> MainEvent {
+        let user = msg::source();
+        Self::store_selection(user, &selected_nfts);
+
+        self.notify(MainEvent::NFTsSelected {
+            user,
+            selected_nfts,
+            seq: Self::next_event_seq(),
+        })
+    }
+
+    /// An authorized delegate sets `user`'s selection, with the same checks as
+    /// `nfts_selected_by_user`
+    pub fn nfts_selected_for(&mut self, user: ActorId, selected_nfts: Vec<(ActorId, U256)>) -> MainEvent {
+        let delegate = msg::source();
+        let authorized = MainContractState::state_ref()
+            .delegates
+            .get(&user)
+            .is_some_and(|delegates| delegates.contains(&delegate));
+        if !authorized {
+            panic!("{:?}", MainError::Unauthorized);
+        }
+        Self::store_selection(user, &selected_nfts);
+
+        self.notify(MainEvent::NFTsSelectedFor {
+            user,
+            delegate,
+            selected_nfts,
+            seq: Self::next_event_seq(),
+        })
+    }
+
+    /// Allows `delegate` to set the caller's selection
+    pub fn authorize_delegate(&mut self, delegate: ActorId) -> MainEvent {
+        let user = msg::source();
+        let delegates = MainContractState::state_mut().delegates.entry(user).or_default();
+        if !delegates.contains(&delegate) {
+            delegates.push(delegate);
+        }
+
+        self.notify(MainEvent::DelegateAuthorized {
+            user,
+            delegate,
+            seq: Self::next_event_seq(),
+        })
+    }
+
+    /// Withdraws a delegate's permission to set the caller's selection
+    pub fn revoke_delegate(&mut self, delegate: ActorId) -> MainEvent {
+        let state = MainContractState::state_mut();
+        let user = msg::source();
+        if let Some(delegates) = state.delegates.get_mut(&user) {
+            delegates.retain(|d| *d != delegate);
+            if delegates.is_empty() {
+                state.delegates.remove(&user);
+            }
+        }
+
+        self.notify(MainEvent::DelegateRevoked {
+            user,
+            delegate,
+            seq: Self::next_event_seq(),
+        })
+    }
+
+    /// Clears the caller's selection, a no-op replying with seq 0 if there is none
+    /// Selected tokens are never locked by this contract, so there is nothing to unlock
+    pub fn cancel_selection(&mut self) -> MainEvent {
+        let state = MainContractState::state_mut();
+        let user = msg::source();
+
+        if state.user_nft_selections.remove(&user).is_none() {
+            return MainEvent::SelectionCancelled {
+                user,
+                seq: NO_EVENT_SEQ,
+            };
+        }
+        Self::mark_active(state, user);
+
+        self.notify(MainEvent::SelectionCancelled {
+            user,
+            seq: Self::next_event_seq(),
+        })
+    }
+
+    /// Query: runs the `nfts_selected_by_user` checks without storing anything
+    pub fn validate_selection(&self, selected_nfts: Vec<(ActorId, U256)>) -> Result<(), MainError> {
+        Self::check_selection(msg::source(), &selected_nfts)
+    }
+
+    /// Adds an admin (only admins can do this)
+    /// Rejected callers get an `UnauthorizedAttempt` breadcrumb instead of a panic,
+    /// since events don't survive a panic
+    pub fn add_admin(&mut self, new_admin: ActorId) -> Result<MainEvent, MainError> {
+        let state = MainContractState::state_mut();
+        let caller = msg::source();
+        if !state.admins.contains(&caller) {
+            self.notify(MainEvent::UnauthorizedAttempt {
+                caller,
+                action: "add_admin".into(),
+                seq: Self::next_event_seq(),
+            });
+            return Err(MainError::Unauthorized);
+        }
+        if !state.admins.contains(&new_admin) {
+            state.admins.push(new_admin);
+            state.admin_added_by.insert(new_admin, caller);
+        }
+        Ok(self.notify(MainEvent::AdminAdded(new_admin, Self::next_event_seq())))
+    }
+
+    /// Proposes handing sole control to `new_admin` (only admins can do this)
+    /// Nothing changes until `new_admin` calls `accept_admin_role`
+    pub fn transfer_admin_role(&mut self, new_admin: ActorId) -> MainEvent {
+        Self::ensure_is_admin();
+        let by = msg::source();
+        MainContractState::state_mut().pending_admin = Some((new_admin, by));
+
+        self.notify(MainEvent::AdminTransferProposed {
+            new_admin,
+            by,
+            seq: Self::next_event_seq(),
+        })
+    }
+
+    /// Completes a pending handoff, the caller becomes the only admin
+    /// `admin_added_by` keeps the former admins' entries as history
+    pub fn accept_admin_role(&mut self) -> MainEvent {
+        let state = MainContractState::state_mut();
+        let new_admin = msg::source();
+        let by = match state.pending_admin {
+            Some((pending, by)) if pending == new_admin => by,
+            _ => panic!("{:?}", MainError::Unauthorized),
+        };
+        state.pending_admin = None;
+        state.admins = vec![new_admin];
+        state.admin_added_by.insert(new_admin, by);
+
+        self.notify(MainEvent::AdminRoleTransferred {
+            new_admin,
+            seq: Self::next_event_seq(),
+        })
+    }
+
+    /// Wipes a stuck user's selection, run stats and weapon (only admins can do this)
+    /// The keyring entry is kept
+    pub fn admin_reset_user(&mut self, user: ActorId) -> MainEvent {
+        Self::ensure_is_admin();
+        let state = MainContractState::state_mut();
+
+        state.user_nft_selections.remove(&user);
+        state.last_run_stats.remove(&user);
+        state.current_weapon.remove(&user);
+        state.last_selection_block.remove(&user);
+
+        self.notify(MainEvent::UserReset(user, Self::next_event_seq()))
+    }
+
+    /// Sets the hash used for run signatures (only admins can do this)
+    pub fn set_hash_algo(&mut self, hash_algo: HashAlgo) -> MainEvent {
+        Self::ensure_is_admin();
+        MainContractState::state_mut().hash_algo = hash_algo;
+
+        self.notify(MainEvent::HashAlgoSet(hash_algo, Self::next_event_seq()))
+    }
+
+    /// Allows selecting NFTs from the given contract (only admins can do this)
+    pub fn add_allowed_contract(&mut self, nft_contract_id: ActorId) -> MainEvent {
+        Self::ensure_is_admin();
+        let state = MainContractState::state_mut();
+        if !state.allowed_nft_contracts.contains(&nft_contract_id) {
+            state.allowed_nft_contracts.push(nft_contract_id);
+        }
+
+        self.notify(MainEvent::AllowedContractAdded(nft_contract_id, Self::next_event_seq()))
+    }
+
+    /// Removes the contract from the allowlist (only admins can do this)
+    pub fn remove_allowed_contract(&mut self, nft_contract_id: ActorId) -> MainEvent {
+        Self::ensure_is_admin();
+        MainContractState::state_mut()
+            .allowed_nft_contracts
+            .retain(|cid| *cid != nft_contract_id);
+
+        self.notify(MainEvent::AllowedContractRemoved(nft_contract_id, Self::next_event_seq()))
+    }
+
+    /// Sets the unit `survival_time` is submitted in (only admins can do this)
+    pub fn set_survival_time_unit(&mut self, unit: TimeUnit) -> MainEvent {
+        Self::ensure_is_admin();
+        MainContractState::state_mut().survival_time_unit = unit;
+
+        self.notify(MainEvent::SurvivalTimeUnitSet(unit, Self::next_event_seq()))
+    }
+
+    /// Turns event emission on or off, e.g. for load testing (only admins can do this)
+    /// `EmitEventsSet` is emitted before muting and after unmuting, so the seq gaps of
+    /// silent periods are accounted for
+    pub fn set_emit_events(&mut self, emit_events: bool) -> MainEvent {
+        Self::ensure_is_admin();
+        MainContractState::state_mut().emit_events |= emit_events;
+        let event = self.notify(MainEvent::EmitEventsSet(emit_events, Self::next_event_seq()));
+        MainContractState::state_mut().emit_events = emit_events;
+        event
+    }
+
+    /// Sets the highest item id accepted in run results (only admins can do this)
+    pub fn set_max_item_id(&mut self, max_item_id: U256) -> MainEvent {
+        Self::ensure_is_admin();
+        MainContractState::state_mut().max_item_id = max_item_id;
+
+        self.notify(MainEvent::MaxItemIdSet(max_item_id, Self::next_event_seq()))
+    }
+
+    /// Sets how many blocks a user must wait between selections (only admins can do this)
+    pub fn set_selection_cooldown(&mut self, blocks: u32) -> MainEvent {
+        Self::ensure_is_admin();
+        MainContractState::state_mut().selection_cooldown_blocks = blocks;
+
+        self.notify(MainEvent::SelectionCooldownSet(blocks, Self::next_event_seq()))
+    }
+
+    /// Sets the vNFT contract weapon ownership is checked against, `None` disables the check
+    /// (only admins can do this)
+    pub fn set_weapon_contract(&mut self, weapon_contract: Option<ActorId>) -> MainEvent {
+        Self::ensure_is_admin();
+        MainContractState::state_mut().weapon_contract = weapon_contract;
+
+        self.notify(MainEvent::WeaponContractSet(weapon_contract, Self::next_event_seq()))
+    }
+
+    /// Sets the vNFT contract `finish_run` mints rewards on, `None` disables minting
+    /// (only admins can do this, the main contract must be set on the vNFT side)
+    pub fn set_reward_contract(&mut self, reward_contract: Option<ActorId>) -> MainEvent {
+        Self::ensure_is_admin();
+        MainContractState::state_mut().reward_contract = reward_contract;
+
+        self.notify(MainEvent::RewardContractSet(reward_contract, Self::next_event_seq()))
+    }
+
+    /// Sets the prefix of reward token media, the item id is appended to it
+    /// (only admins can do this), vNFT contracts with allowed media schemes reject
+    /// the empty default
+    pub fn set_reward_media_base(&mut self, reward_media_base: String) -> MainEvent {
+        Self::ensure_is_admin();
+        MainContractState::state_mut().reward_media_base = reward_media_base.clone();
+
+        self.notify(MainEvent::RewardMediaBaseSet(reward_media_base, Self::next_event_seq()))
+    }
+
+    /// Sets the longest public key the keyring accepts, in bytes (only admins can do this)
+    pub fn set_max_key_len(&mut self, max_key_len: u32) -> MainEvent {
+        Self::ensure_is_admin();
+        MainContractState::state_mut().max_key_len = max_key_len;
+
+        self.notify(MainEvent::MaxKeyLenSet(max_key_len, Self::next_event_seq()))
+    }
+
+    /// Caps how many participants a run may have, zero removes the cap (only admins can do this)
+    pub fn set_max_participants(&mut self, max_participants: u32) -> MainEvent {
+        Self::ensure_is_admin();
+        MainContractState::state_mut().max_participants = max_participants;
+
+        self.notify(MainEvent::MaxParticipantsSet(max_participants, Self::next_event_seq()))
+    }
+
+    /// Mints the `new_items_selected` of the user's stats for `run_id` as reward tokens on
+    /// the vNFT contract with a single `MintBatch` call (only admins can do this, the main
+    /// contract must be set on the vNFT side)
+    /// The stats are looked up in `run_history`, pruned or dropped runs fail with `RunNotFound`
+    /// Runs that were already awarded, here or by `finish_run`, fail with `AlreadyAwarded`
+    /// A failed mint returns `VnftCallFailed` and can be retried
+    pub async fn admin_award_items(
+        &mut self,
+        vnft_contract: ActorId,
+        run_id: u64,
+        user: ActorId,
+    ) -> Result<Vec<U256>, MainError> {
+        Self::ensure_is_admin();
+        let item_ids = MainContractState::state_ref()
+            .run_history
+            .iter()
+            .rev()
+            .find(|stats| stats.run_id == run_id && stats.user == user)
+            .map(|stats| stats.new_items_selected.clone())
+            .ok_or(MainError::RunNotFound)?;
+        Self::award_run(vnft_contract, run_id, user, item_ids).await
+    }
+
+    /// Query: reward token ids minted for the user's stats of `run_id`, empty if none were
+    pub fn query_awarded_rewards(&self, run_id: u64, user: ActorId) -> Vec<U256> {
+        MainContractState::state_ref()
+            .awarded_rewards
+            .get(&(run_id, user))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Removes run stats recorded before `older_than_block` (only admins can do this)
+    pub fn admin_prune_run_stats(&mut self, older_than_block: u32) -> MainEvent {
+        Self::ensure_is_admin();
+        let state = MainContractState::state_mut();
+
+        let before = state.last_run_stats.len();
+        state.last_run_stats.retain(|_, stats| stats.block_height >= older_than_block);
+        let removed = (before - state.last_run_stats.len()) as u32;
+        state.run_history.retain(|stats| stats.block_height >= older_than_block);
+        let kept_runs: HashSet<(u64, ActorId)> = state
+            .run_history
+            .iter()
+            .map(|stats| (stats.run_id, stats.user))
+            .collect();
+        state.awarded_rewards.retain(|key, _| kept_runs.contains(key));
+
+        self.notify(MainEvent::RunStatsPruned {
+            removed,
+            seq: Self::next_event_seq(),
+        })
+    }
+
+    /// Query: runs recorded within `[from_block, to_block]`, oldest first
+    /// Skips the first `offset` matches and returns at most `MAX_QUERY_RUNS`, so a full
+    /// range is read by raising `offset` until a reply comes back short
+    pub fn query_runs_in_range(
+        &self,
+        from_block: u32,
+        to_block: u32,
+        offset: u32,
+    ) -> Vec<RunStats> {
+        if from_block > to_block {
+            panic!("{:?}", MainError::InvalidBlockRange);
+        }
+        MainContractState::state_ref()
+            .run_history
+            .iter()
+            .filter(|stats| (from_block..=to_block).contains(&stats.block_height))
+            .skip(offset as usize)
+            .take(MAX_QUERY_RUNS)
+            .cloned()
+            .collect()
+    }
+
+    /// Query: gets NFTs selected by a user (returns only the token IDs)
+    pub fn query_user_nfts(&self, user: ActorId) -> Option<Vec<U256>> {
+        MainContractState::state_ref()
+            .user_nft_selections
+            .get(&user)
+            .map(|nfts| nfts.iter().map(|(_cid, tid)| *tid).collect())
+    }
+
+    /// Query: gets NFTs selected by a user as (nft_contract_id, token_id) tuples
+    /// Unlike `query_user_nfts` this keeps same token ids from different contracts apart
+    pub fn query_user_nfts_full(&self, user: ActorId) -> Vec<(ActorId, U256)> {
+        MainContractState::state_ref()
+            .user_nft_selections
+            .get(&user)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Query: the user's selection, last run stats, weapon and keyring presence in one call
+    pub fn query_user_profile(&self, user: ActorId) -> UserProfile {
+        let state = MainContractState::state_ref();
+        UserProfile {
+            selected_nfts: state.user_nft_selections.get(&user).cloned().unwrap_or_default(),
+            last_run_stats: state.last_run_stats.get(&user).cloned(),
+            current_weapon: state.current_weapon.get(&user).copied(),
+            has_key: state.keyring.contains_key(&user),
+            display_name: state.display_names.get(&user).cloned(),
+        }
+    }
+
+    /// Query: the user's selected token ids grouped by NFT contract, in first-seen order
+    pub fn query_user_selection_by_contract(&self, user: ActorId) -> Vec<(ActorId, Vec<U256>)> {
+        let mut grouped: Vec<(ActorId, Vec<U256>)> = Vec::new();
+        let Some(nfts) = MainContractState::state_ref().user_nft_selections.get(&user) else {
+            return grouped;
+        };
+        for (cid, tid) in nfts {
+            match grouped.iter_mut().find(|(contract, _)| contract == cid) {
+                Some((_, token_ids)) => token_ids.push(*tid),
+                None => grouped.push((*cid, vec![*tid])),
+            }
+        }
+        grouped
+    }
+
+    /// Fetches the metadata of every token selected by the user from its vNFT contract
+    /// Tokens that no longer exist are skipped
+    /// Queries can't wait on other contracts, so this has to be sent as a message
+    pub async fn resolve_selection_metadata(&mut self, user: ActorId) -> Vec<(U256, TokenMetadata)> {
+        let selected_nfts = MainContractState::state_ref()
+            .user_nft_selections
+            .get(&user)
+            .cloned()
+            .unwrap_or_default();
+
+        let mut resolved = Vec::with_capacity(selected_nfts.len());
+        for (cid, tid) in selected_nfts {
+            let metadata: Option<TokenMetadata> =
+                Self::vnft_call(cid, "TokenMetadataById", tid).await;
+            if let Some(metadata) = metadata {
+                resolved.push((tid, metadata));
+            }
+        }
+        resolved
+    }
+
+    /// Query: users whose latest activity is at or after `since_block`
+    pub fn query_active_since(&self, since_block: u32) -> Vec<ActorId> {
+        MainContractState::state_ref()
+            .last_active_block
+            .iter()
+            .filter(|(_, block)| **block >= since_block)
+            .map(|(user, _)| *user)
+            .collect()
+    }
+
+    /// The user's selected tokens that no longer exist on their vNFT contract,
+    /// e.g. because they were burned after being selected
+    /// Queries can't wait on other contracts, so this has to be sent as a message
+    pub async fn query_stale_selections(&mut self, user: ActorId) -> Vec<(ActorId, U256)> {
+        let selected_nfts = MainContractState::state_ref()
+            .user_nft_selections
+            .get(&user)
+            .cloned()
+            .unwrap_or_default();
+
+        let mut stale = Vec::new();
+        for (cid, tid) in selected_nfts {
+            let metadata: Option<TokenMetadata> =
+                Self::vnft_call(cid, "TokenMetadataById", tid).await;
+            if metadata.is_none() {
+                stale.push((cid, tid));
+            }
+        }
+        stale
+    }
+
+    /// Query: get all users which have selected NFTs and their selections
+    pub fn query_all_user_selections(&self) -> Vec<UserSelection> {
+        MainContractState::state_ref()
+            .user_nft_selections
+            .iter()
+            .map(|(k, v)| UserSelection {
+                user: *k,
+                selected_nfts: v.clone(),
+            })
+            .collect()
+    }
+
+    /// Query: number of users which have selected NFTs
+    pub fn query_user_selections_count(&self) -> u32 {
+        MainContractState::state_ref().user_nft_selections.len() as u32
+    }
+
+    /// Query: a page of user selections ordered by user, plus the total count
+    pub fn query_user_selections_page(&self, offset: u32, limit: u32) -> (Vec<UserSelection>, u32) {
+        let state = MainContractState::state_ref();
+        let mut users: Vec<&ActorId> = state.user_nft_selections.keys().collect();
+        users.sort();
+        let page = users
+            .into_iter()
+            .skip(offset as usize)
+            .take(limit as usize)
+            .map(|user| UserSelection {
+                user: *user,
+                selected_nfts: state.user_nft_selections[user].clone(),
+            })
+            .collect();
+        (page, state.user_nft_selections.len() as u32)
+    }
+
+    /// Query: totals for dashboards, computed in a single pass over the selections
+    pub fn query_stats(&self) -> GameStats {
+        let state = MainContractState::state_ref();
+        let nfts_selected = state.user_nft_selections
+            .values()
+            .map(|nfts| nfts.len() as u32)
+            .fold(0u32, |total, len| total.saturating_add(len));
+        GameStats {
+            users_with_selections: state.user_nft_selections.len() as u32,
+            admins: state.admins.len() as u32,
+            runs_finished: state.runs_finished,
+            nfts_selected,
+        }
+    }
+
+    /// Query: how many times each NFT contract appears across all selections,
+    /// most selected first
+    pub fn query_contract_selection_counts(&self) -> Vec<(ActorId, u32)> {
+        let mut counts: HashMap<ActorId, u32> = HashMap::new();
+        for nfts in MainContractState::state_ref().user_nft_selections.values() {
+            for (cid, _tid) in nfts {
+                let count = counts.entry(*cid).or_insert(0);
+                *count = count.saturating_add(1);
+            }
+        }
+        let mut counts: Vec<(ActorId, u32)> = counts.into_iter().collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        counts
+    }
+
+    /// Query: number of distinct NFT contracts across all selections
+    pub fn query_distinct_selected_contracts(&self) -> u32 {
+        MainContractState::state_ref()
+            .user_nft_selections
+            .values()
+            .flatten()
+            .map(|(cid, _tid)| *cid)
+            .collect::<HashSet<ActorId>>()
+            .len() as u32
+    }
+
+    /// Query: the user's 1-based rank by `monsters_defeated` in their last run,
+    /// ties go to the lower account id, `None` without a recorded run
+    pub fn query_user_rank(&self, user: ActorId) -> Option<u32> {
+        let last_run_stats = &MainContractState::state_ref().last_run_stats;
+        let monsters = last_run_stats.get(&user)?.monsters_defeated;
+        let ahead = last_run_stats
+            .iter()
+            .filter(|(other, stats)| {
+                stats.monsters_defeated > monsters
+                    || (stats.monsters_defeated == monsters && **other < user)
+            })
+            .count() as u32;
+        Some(ahead + 1)
+    }
+
+    /// Query: returns all admins
+    pub fn query_admins(&self) -> Vec<ActorId> {
+        MainContractState::state_ref().admins.clone()
+    }
+
+    /// Query: number of admins
+    pub fn admin_count(&self) -> u32 {
+        MainContractState::state_ref().admins.len() as u32
+    }
+
+    /// Query: whether `who` is an admin
+    pub fn is_admin(&self, who: ActorId) -> bool {
+        MainContractState::state_ref().admins.contains(&who)
+    }
+
+    /// Query: the admin who added `admin`, the seed admin is its own adder
+    pub fn query_admin_adder(&self, admin: ActorId) -> Option<ActorId> {
+        MainContractState::state_ref().admin_added_by.get(&admin).copied()
+    }
+
+    /// Query: contract version, matches the state schema version
+    pub fn version(&self) -> u32 {
+        STATE_VERSION
+    }
+
+    /// Query: returns full contract state
+    pub fn query_state(&self) -> IoMainContractState {
+        MainContractState::state_ref().into()
+    }
+
+    /// Query: full contract state along with the block height it was read at
+    pub fn query_state_at(&self) -> (IoMainContractState, u32) {
+        (self.query_state(), exec::block_height())
+    }
+
+    /// Query: returns a full snapshot of every mutable field for off-chain backups,
+    /// tagged with `state_version` so tooling can detect schema changes
+    /// `map_key_counts` lets a restore check that no map entries went missing
+    pub fn query_full_snapshot(&self) -> FullSnapshot {
+        let state = MainContractState::state_ref();
+        FullSnapshot {
+            state: state.into(),
+            run_history: state.run_history.iter().cloned().collect(),
+            map_key_counts: state.into(),
+        }
+    }
+
+    /// Starts a run/game session and signs the creation so the player can start
+    pub fn start_run(&mut self) -> MainEvent {
+        // Gather all participants and their NFT selections
+        let participants: Vec<(ActorId, Vec<(ActorId, U256)>)> = MainContractState::state_ref()
+            .user_nft_selections
+            .iter()
+            .map(|(user, nfts)| (*user, nfts.clone()))
+            .collect();
+
+        if participants.is_empty() {
+            panic!("No users have selected NFTs. Cannot start run.");
+        }
+
+        self.begin_run(participants)
+    }
+
+    /// Solo run: stores the caller's selection and starts a run with just them
+    /// Emits `NFTsSelected` followed by `RunStarted`, the latter is returned
+    pub fn select_and_start(&mut self, selected_nfts: Vec<(ActorId, U256)>) -> MainEvent {
+        let user = msg::source();
+        self.nfts_selected_by_user(selected_nfts.clone());
+
+        self.begin_run(vec![(user, selected_nfts)])
+    }
+
+    /// Starts a run with only the given users, each must have a current selection
+    /// Repeated users are dropped, only their first occurrence is kept
+    pub fn start_run_with(&mut self, participants: Vec<ActorId>) -> MainEvent {
+        if participants.is_empty() {
+            panic!("No participants given. Cannot start run.");
+        }
+        let state = MainContractState::state_ref();
+        let mut selected: Vec<(ActorId, Vec<(ActorId, U256)>)> = Vec::with_capacity(participants.len());
+        for user in participants {
+            if selected.iter().any(|(seen, _)| *seen == user) {
+                continue;
+            }
+            let nfts = state
+                .user_nft_selections
+                .get(&user)
+                .unwrap_or_else(|| panic!("{:?}", MainError::NoSelection));
+            selected.push((user, nfts.clone()));
+        }
+
+        self.begin_run(selected)
+    }
+
+    /// Query: the signature `start_run` would produce for these participants,
+    /// with the caller as initiator and the current hash algorithm
+    pub fn preview_run_signature(
+        &self,
+        participants: Vec<(ActorId, Vec<(ActorId, U256)>)>,
+    ) -> [u8; 32] {
+        Self::run_signature(
+            MainContractState::state_ref().hash_algo,
+            msg::source(),
+            &participants,
+        )
+    }
+
+    /// Query: whether the stats would pass the `finish_run` anti-cheat limits
+    pub fn validate_run_stats(
+        &self,
+        monsters_defeated: u32,
+        items_found_len: u32,
+        new_items_len: u32,
+        survival_time: u64,
+    ) -> Result<(), MainError> {
+        Self::check_run_stats(
+            monsters_defeated,
+            items_found_len as usize,
+            new_items_len as usize,
+            survival_time,
+        )
+    }
+
+    /// Called when a run finishes to submit stats
+    /// Only participants captured by `start_run` can submit stats for that run, once each
+    /// The run is closed once every participant has submitted
+    /// With a `reward_contract` set, `new_items_selected` are minted to the user there
+    /// The stats are kept if minting fails, `RewardAwardFailed` is emitted and admins can
+    /// then retry with `admin_award_items`
+    pub async fn finish_run(
+        &mut self,
+        run_id: u64,
+        monsters_defeated: u32,
+        items_found: Vec<U256>,
+        new_items_selected: Vec<U256>,
+        survival_time: u64,
+    ) -> MainEvent {
+        let state = MainContractState::state_mut();
+        let user = msg::source();
+
+        if let Err(err) = Self::check_run_stats(
+            monsters_defeated,
+            items_found.len(),
+            new_items_selected.len(),
+            survival_time,
+        ) {
+            panic!("Invalid stats: {err:?}");
+        }
+        if let Err(err) = Self::validate_item_ids(&items_found, &new_items_selected) {
+            panic!("Invalid stats: {err:?}");
+        }
+
+        let initiator = Self::claim_run_result(state, run_id, user);
+
+        let stats = RunStats {
+            user,
+            monsters_defeated,
+            items_found: items_found.clone(),
+            new_items_selected: new_items_selected.clone(),
+            survival_time,
+            block_height: exec::block_height(),
+            initiator,
+            run_id,
+        };
+        Self::record_run_stats(state, user, stats);
+
+        let awarded_token_ids = match state.reward_contract {
+            Some(reward_contract) => {
+                match Self::award_run(reward_contract, run_id, user, new_items_selected.clone())
+                    .await
+                {
+                    Ok(token_ids) => token_ids,
+                    Err(error) => {
+                        self.notify(MainEvent::RewardAwardFailed {
+                            user,
+                            run_id,
+                            error,
+                            seq: Self::next_event_seq(),
+                        });
+                        Vec::new()
+                    }
+                }
+            }
+            None => Vec::new(),
+        };
+
+        self.notify(MainEvent::RunFinished {
+            user,
+            monsters_defeated,
+            items_found,
+            new_items_selected,
+            survival_time,
+            initiator,
+            awarded_token_ids,
+            seq: Self::next_event_seq(),
+        })
+    }
+
+    /// Closes an active run without submitting stats (initiator or admins only)
+    /// An aborted run can't be finished afterwards, both then fail with `RunAlreadyFinished`
+    pub fn abort_run(&mut self, run_id: u64) -> MainEvent {
+        let state = MainContractState::state_mut();
+        let by = msg::source();
+
+        let Some(run) = state.active_runs.get(&run_id) else {
+            panic!("{:?}", Self::missing_run_error(state, run_id));
+        };
+        if run.initiator != by && !state.admins.contains(&by) {
+            panic!("{:?}", MainError::Unauthorized);
+        }
+        Self::close_run(state, run_id);
+
+        self.notify(MainEvent::RunAborted {
+            run_id,
+            by,
+            seq: Self::next_event_seq(),
+        })
+    }
+
+    /// Sets a new selected weapon for the user
+    /// Re-selecting the current weapon is a no-op, emits no event and replies with seq 0
+    /// With a `weapon_contract` set, the user must own the token there
+    pub async fn set_new_selected_weapon(&mut self, token_id: U256) -> MainEvent {
+        let user = msg::source();
+
+        let state = MainContractState::state_ref();
+        if state.current_weapon.get(&user) == Some(&token_id) {
+            return MainEvent::NewWeaponSelected {
+                user,
+                token_id,
+                seq: NO_EVENT_SEQ,
+            };
+        }
+        if let Some(weapon_contract) = state.weapon_contract {
+            let owner: ActorId = Self::vnft_call(weapon_contract, "OwnerOf", token_id).await;
+            if owner != user {
+                panic!("{:?}", MainError::NotWeaponOwner);
+            }
+        }
+
+        let state = MainContractState::state_mut();
+        state.current_weapon.insert(user, token_id);
+        Self::mark_active(state, user);
+
+        self.notify(MainEvent::NewWeaponSelected {
+            user,
+            token_id,
+            seq: Self::next_event_seq(),
+        })
+    }
+
+    /// Returns the new stats to the user after the run finishes, along with how they
+    /// compare to the previous run
+    /// The caller must be a participant of the open run `run_id` and may submit once, like
+    /// with `finish_run`, `user`, `initiator` and `run_id` are overwritten
+    pub fn run_finished(&mut self, run_id: u64, new_status: RunStats) -> RunResult {
+        // Anti-cheat validation
+        if let Err(err) = Self::check_run_stats(
+            new_status.monsters_defeated,
+            new_status.items_found.len(),
+            new_status.new_items_selected.len(),
+            new_status.survival_time,
+        ) {
+            panic!("Invalid stats: {err:?}");
+        }
+        if let Err(err) = Self::validate_item_ids(&new_status.items_found, &new_status.new_items_selected) {
+            panic!("Invalid stats: {err:?}");
+        }
+
+        let state = MainContractState::state_mut();
+        let user = msg::source();
+
+        // Take the initiator from the run session, not from the caller
+        let initiator = Self::claim_run_result(state, run_id, user);
+
+        let (monsters_defeated_delta, survival_time_delta) = match state.last_run_stats.get(&user) {
+            Some(previous) => (
+                i64::from(new_status.monsters_defeated) - i64::from(previous.monsters_defeated),
+                Self::survival_time_delta(new_status.survival_time, previous.survival_time)
+                    .unwrap_or_else(|err| panic!("{:?}", err)),
+            ),
+            None => (0, 0),
+        };
+
+        // Store the new stats for the user
+        let stats = RunStats {
+            user,
+            block_height: exec::block_height(),
+            initiator,
+            run_id,
+            ..new_status
+        };
+        Self::record_run_stats(state, user, stats.clone());
+
+        // Return the new stats to the user
+        RunResult {
+            stats,
+            monsters_defeated_delta,
+            survival_time_delta,
+        }
+    }
+
+    /// Keyring: Add a public key for the user
+    pub fn add_key(&mut self, public_key: Vec<u8>, metadata: Option<String>) -> MainEvent {
+        if let Err(err) = Self::check_public_key(&public_key) {
+            panic!("{:?}", err);
+        }
+        let state = MainContractState::state_mut();
+        let user = msg::source();
 
         if state.keyring.contains_key(&user) {
             panic!("Key already exists for user");
@@ -388,22 +1822,17 @@ impl Service {
         };
         state.keyring.insert(user, entry);
 
-        self.emit_event(MainEvent::KeyAdded {
-            user,
-            public_key,
-            metadata,
-        }).expect("Failed to emit event");
-
-        MainEvent::KeyAdded {
+        self.notify(MainEvent::KeyAdded {
             user,
             public_key,
             metadata,
-        }
+            seq: Self::next_event_seq(),
+        })
     }
 
     /// Keyring: Remove the public key for the user
     pub fn remove_key(&mut self) -> MainEvent {
-        let mut state = MainContractState::state_mut();
+        let state = MainContractState::state_mut();
         let user = msg::source();
 
         if !state.keyring.contains_key(&user) {
@@ -412,18 +1841,32 @@ impl Service {
 
         state.keyring.remove(&user);
 
-        self.emit_event(MainEvent::KeyRemoved {
+        self.notify(MainEvent::KeyRemoved {
             user,
-        }).expect("Failed to emit event");
+            origin: KeyOrigin::RemoveKey,
+            seq: Self::next_event_seq(),
+        })
+    }
+
+    /// Keyring: Wipe a user's key, e.g. after it was compromised (only admins can do this)
+    /// Succeeds even if the user has no key
+    pub fn admin_remove_key(&mut self, user: ActorId) -> MainEvent {
+        Self::ensure_is_admin();
+        MainContractState::state_mut().keyring.remove(&user);
 
-        MainEvent::KeyRemoved {
+        self.notify(MainEvent::KeyRemoved {
             user,
-        }
+            origin: KeyOrigin::AdminRemoveKey,
+            seq: Self::next_event_seq(),
+        })
     }
 
     /// Keyring: Update the public key for the user
     pub fn update_key(&mut self, public_key: Vec<u8>, metadata: Option<String>) -> MainEvent {
-        let mut state = MainContractState::state_mut();
+        if let Err(err) = Self::check_public_key(&public_key) {
+            panic!("{:?}", err);
+        }
+        let state = MainContractState::state_mut();
         let user = msg::source();
 
         if !state.keyring.contains_key(&user) {
@@ -437,17 +1880,71 @@ impl Service {
         };
         state.keyring.insert(user, entry);
 
-        self.emit_event(MainEvent::KeyUpdated {
+        self.notify(MainEvent::KeyUpdated {
             user,
             public_key,
             metadata,
-        }).expect("Failed to emit event");
+            origin: KeyOrigin::UpdateKey,
+            seq: Self::next_event_seq(),
+        })
+    }
+
+    /// Keyring: Change only the metadata label of the user's key, the key itself is kept
+    pub fn update_key_metadata(&mut self, metadata: Option<String>) -> MainEvent {
+        let state = MainContractState::state_mut();
+        let user = msg::source();
 
-        MainEvent::KeyUpdated {
+        let Some(entry) = state.keyring.get_mut(&user) else {
+            panic!("No key exists for user");
+        };
+        entry.metadata = metadata.clone();
+        let public_key = entry.public_key.clone();
+
+        self.notify(MainEvent::KeyUpdated {
             user,
             public_key,
             metadata,
+            origin: KeyOrigin::UpdateKeyMetadata,
+            seq: Self::next_event_seq(),
+        })
+    }
+
+    /// Keyring: Rotate the user's key, proving control of the stored one
+    /// `signature` is an sr25519 signature of `new_public_key` made with the old key,
+    /// either over the raw bytes or `<Bytes>`-wrapped as polkadot.js `signRaw` produces
+    pub fn update_key_signed(
+        &mut self,
+        new_public_key: Vec<u8>,
+        signature: Vec<u8>,
+        metadata: Option<String>,
+    ) -> MainEvent {
+        if let Err(err) = Self::check_public_key(&new_public_key) {
+            panic!("{:?}", err);
         }
+        let state = MainContractState::state_mut();
+        let user = msg::source();
+
+        let Some(entry) = state.keyring.get(&user) else {
+            panic!("{:?}", MainError::NoKey);
+        };
+        if !Self::verify_sr25519(&entry.public_key, &new_public_key, &signature) {
+            panic!("{:?}", MainError::InvalidSignature);
+        }
+
+        let entry = KeyringEntry {
+            user,
+            public_key: new_public_key.clone(),
+            metadata: metadata.clone(),
+        };
+        state.keyring.insert(user, entry);
+
+        self.notify(MainEvent::KeyUpdated {
+            user,
+            public_key: new_public_key,
+            metadata,
+            origin: KeyOrigin::UpdateKeySigned,
+            seq: Self::next_event_seq(),
+        })
     }
 
     /// Keyring: Query the public key for a user
@@ -458,6 +1955,73 @@ impl Service {
             .cloned()
     }
 
+    /// Query: how many runs have been started, run ids are handed out sequentially
+    pub fn query_total_runs_started(&self) -> u64 {
+        MainContractState::state_ref().next_run_id
+    }
+
+    /// Query: ids of the runs the user started
+    pub fn query_runs_initiated_by(&self, user: ActorId) -> Vec<u64> {
+        MainContractState::state_ref()
+            .runs_initiated
+            .get(&user)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Query: ids of the runs the user took part in as a participant
+    pub fn query_runs_joined_by(&self, user: ActorId) -> Vec<u64> {
+        MainContractState::state_ref()
+            .runs_joined
+            .get(&user)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Query: returns a run that has been started
+    pub fn query_run(&self, run_id: u64) -> Option<RunSession> {
+        MainContractState::state_ref()
+            .active_runs
+            .get(&run_id)
+            .cloned()
+    }
+
+    /// Sets the caller's leaderboard name, overwriting any previous one
+    /// Empty, over-long or control-character names are rejected
+    pub fn set_display_name(&mut self, name: String) -> MainEvent {
+        if name.is_empty()
+            || name.len() > MAX_DISPLAY_NAME_LEN
+            || name.chars().any(char::is_control)
+        {
+            panic!("{:?}", MainError::InvalidDisplayName);
+        }
+        let user = msg::source();
+        MainContractState::state_mut()
+            .display_names
+            .insert(user, name.clone());
+
+        self.notify(MainEvent::DisplayNameSet {
+            user,
+            name,
+            seq: Self::next_event_seq(),
+        })
+    }
+
+    /// Query: the user's leaderboard name, if set
+    pub fn query_display_name(&self, user: ActorId) -> Option<String> {
+        MainContractState::state_ref()
+            .display_names
+            .get(&user)
+            .cloned()
+    }
+
+    /// Keyring: Whether the user has a key, without returning the key itself
+    pub fn has_key(&self, user: ActorId) -> bool {
+        MainContractState::state_ref()
+            .keyring
+            .contains_key(&user)
+    }
+
     /// Keyring: Query all keyring entries
     pub fn query_all_keys(&self) -> Vec<KeyringEntry> {
         MainContractState::state_ref()
@@ -467,3 +2031,128 @@ impl Service {
             .collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use schnorrkel::{ExpansionMode, Keypair, MiniSecretKey};
+
+    #[test]
+    fn checked_increment_reaches_u64_max() {
+        assert_eq!(Service::checked_increment(0), 1);
+        assert_eq!(Service::checked_increment(u64::MAX - 1), u64::MAX);
+    }
+
+    #[test]
+    #[should_panic(expected = "Overflow")]
+    fn checked_increment_overflow() {
+        Service::checked_increment(u64::MAX);
+    }
+
+    #[test]
+    fn survival_time_delta_near_u64_max() {
+        assert_eq!(Service::survival_time_delta(u64::MAX, u64::MAX - 5), Ok(5));
+        assert_eq!(Service::survival_time_delta(u64::MAX - 5, u64::MAX), Ok(-5));
+        assert_eq!(Service::survival_time_delta(i64::MAX as u64, 0), Ok(i64::MAX));
+        assert_eq!(Service::survival_time_delta(u64::MAX, 0), Err(MainError::Overflow));
+        assert_eq!(Service::survival_time_delta(0, u64::MAX), Err(MainError::Overflow));
+    }
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+    }
+
+    #[test]
+    fn run_signature_keccak_digest() {
+        // keccak256 of 32 zero bytes, the encoded zero initiator
+        let signature = Service::run_signature(HashAlgo::Keccak, ActorId::zero(), &[]);
+        assert_eq!(
+            hex(&signature),
+            "290decd9548b62a8d60345a988386fc84ba6bc95484008f6362f93160ef3e563"
+        );
+    }
+
+    #[test]
+    fn run_signature_blake2b_digest() {
+        let signature = Service::run_signature(HashAlgo::Blake2b, ActorId::zero(), &[]);
+        assert_eq!(
+            hex(&signature),
+            "89eb0d6a8a691dae2cd15ed0369931ce0a949ecafa5c3f93f8121833646e15c3"
+        );
+    }
+
+    #[test]
+    fn run_signature_covers_participants() {
+        let participants = vec![(ActorId::from(11), vec![(ActorId::from(20), U256::from(1))])];
+        for hash_algo in [HashAlgo::Blake2b, HashAlgo::Keccak] {
+            assert_ne!(
+                Service::run_signature(hash_algo, ActorId::zero(), &participants),
+                Service::run_signature(hash_algo, ActorId::zero(), &[]),
+            );
+        }
+    }
+
+    fn keypair(seed: u8) -> Keypair {
+        MiniSecretKey::from_bytes(&[seed; 32])
+            .unwrap()
+            .expand_to_keypair(ExpansionMode::Ed25519)
+    }
+
+    #[test]
+    fn verify_sr25519_accepts_raw_message() {
+        let old_key = keypair(1);
+        let new_public_key = keypair(2).public.to_bytes();
+        let signature = old_key.sign_simple(SIGNING_CONTEXT, &new_public_key);
+
+        assert!(Service::verify_sr25519(
+            &old_key.public.to_bytes(),
+            &new_public_key,
+            &signature.to_bytes(),
+        ));
+    }
+
+    #[test]
+    fn verify_sr25519_accepts_bytes_wrapped_message() {
+        let old_key = keypair(1);
+        let new_public_key = keypair(2).public.to_bytes();
+        let wrapped = [b"<Bytes>".as_slice(), &new_public_key, b"</Bytes>".as_slice()].concat();
+        let signature = old_key.sign_simple(SIGNING_CONTEXT, &wrapped);
+
+        assert!(Service::verify_sr25519(
+            &old_key.public.to_bytes(),
+            &new_public_key,
+            &signature.to_bytes(),
+        ));
+    }
+
+    #[test]
+    fn verify_sr25519_rejects_wrong_key_and_message() {
+        let old_key = keypair(1);
+        let other_key = keypair(3);
+        let new_public_key = keypair(2).public.to_bytes();
+        let signature = old_key.sign_simple(SIGNING_CONTEXT, &new_public_key);
+
+        assert!(!Service::verify_sr25519(
+            &other_key.public.to_bytes(),
+            &new_public_key,
+            &signature.to_bytes(),
+        ));
+        assert!(!Service::verify_sr25519(
+            &old_key.public.to_bytes(),
+            &other_key.public.to_bytes(),
+            &signature.to_bytes(),
+        ));
+        assert!(!Service::verify_sr25519(
+            &old_key.public.to_bytes(),
+            &new_public_key,
+            &[0; 64],
+        ));
+    }
+
+    #[test]
+    fn max_survival_time_is_24h_in_every_unit() {
+        assert_eq!(TimeUnit::Milliseconds.max_survival_time(), 86_400_000);
+        assert_eq!(TimeUnit::Seconds.max_survival_time(), 86_400);
+        assert_eq!(TimeUnit::Blocks.max_survival_time(), 28_800);
+    }
+}
@@ -0,0 +1,2119 @@
+use client::{
+    service,
+    traits::{Service as _, TemplateFactory as _},
+    GameStats, HashAlgo, KeyOrigin, MainError, MainEvent, RunStats, Service as MainClient,
+    TemplateFactory as Factory, TimeUnit, TokenMetadata,
+};
+use gtest::WasmProgram;
+use schnorrkel::{ExpansionMode, Keypair, MiniSecretKey};
+use sails_rs::calls::*;
+use sails_rs::errors::{Error, RtlError};
+use sails_rs::events::Listener;
+use sails_rs::futures::StreamExt;
+use sails_rs::gtest::{calls::*, Program, System};
+use sails_rs::{ActorId, Decode, Encode, U256};
+use std::{cell::RefCell, collections::BTreeMap, rc::Rc};
+
+#[allow(dead_code)]
+mod client {
+    include!(concat!(env!("OUT_DIR"), "/template_client.rs"));
+
+    // Commands reply with the event type, which the IDL only lists as events
+    pub use service::events::ServiceEvents as MainEvent;
+}
+
+mod code {
+    include!(concat!(env!("OUT_DIR"), "/wasm_binary.rs"));
+}
+
+pub const ADMIN_ID: u64 = 10;
+pub const USER_ID: [u64; 3] = [11, 12, 13];
+
+/// Stand-in for a vNFT contract, answering the calls MainContract makes to it
+/// Its state is shared with the test so tokens can be added or burned from outside
+#[derive(Debug, Default, Clone)]
+struct VnftMock(Rc<RefCell<VnftMockState>>);
+
+#[derive(Debug, Default)]
+struct VnftMockState {
+    tokens: BTreeMap<U256, (ActorId, TokenMetadata)>,
+    next_id: U256,
+    /// Media schemes minted metadata must use, empty allows any media
+    allowed_schemes: Vec<String>,
+    mint_batch_calls: u32,
+}
+
+impl VnftMock {
+    fn mint(&self, to: ActorId, metadata: TokenMetadata) -> U256 {
+        let mut state = self.0.borrow_mut();
+        let token_id = state.next_id;
+        state.next_id = token_id + 1;
+        state.tokens.insert(token_id, (to, metadata));
+        token_id
+    }
+
+    fn burn(&self, token_id: U256) {
+        self.0.borrow_mut().tokens.remove(&token_id);
+    }
+}
+
+impl WasmProgram for VnftMock {
+    fn init(&mut self, _payload: Vec<u8>) -> Result<Option<Vec<u8>>, &'static str> {
+        Ok(None)
+    }
+
+    fn handle(&mut self, payload: Vec<u8>) -> Result<Option<Vec<u8>>, &'static str> {
+        let mut input = payload.as_slice();
+        let (service, method) =
+            <(String, String)>::decode(&mut input).map_err(|_| "Failed to decode route")?;
+        let value = match method.as_str() {
+            "MintBatch" => {
+                let (to, metadata) = <(ActorId, Vec<TokenMetadata>)>::decode(&mut input)
+                    .map_err(|_| "Failed to decode MintBatch")?;
+                {
+                    let mut state = self.0.borrow_mut();
+                    let schemes = &state.allowed_schemes;
+                    if !schemes.is_empty()
+                        && !metadata
+                            .iter()
+                            .all(|m| schemes.iter().any(|scheme| m.media.starts_with(scheme)))
+                    {
+                        return Err("InvalidMetadata");
+                    }
+                    state.mint_batch_calls += 1;
+                }
+                let token_ids: Vec<U256> = metadata
+                    .into_iter()
+                    .map(|metadata| self.mint(to, metadata))
+                    .collect();
+                token_ids.encode()
+            }
+            "TokenMetadataById" => {
+                let token_id = U256::decode(&mut input).map_err(|_| "Failed to decode token id")?;
+                let state = self.0.borrow();
+                state.tokens.get(&token_id).map(|(_, metadata)| metadata.clone()).encode()
+            }
+            "OwnerOf" => {
+                let token_id = U256::decode(&mut input).map_err(|_| "Failed to decode token id")?;
+                let state = self.0.borrow();
+                let (owner, _) = state.tokens.get(&token_id).ok_or("TokenDoesNotExist")?;
+                owner.encode()
+            }
+            _ => return Err("Unknown method"),
+        };
+        Ok(Some([service.encode(), method.encode(), value].concat()))
+    }
+
+    fn handle_reply(&mut self, _payload: Vec<u8>) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    fn handle_signal(&mut self, _payload: Vec<u8>) -> Result<(), &'static str> {
+        Ok(())
+    }
+
+    fn state(&mut self) -> Result<Vec<u8>, &'static str> {
+        Err("No state")
+    }
+}
+
+async fn deploy() -> (GTestRemoting, ActorId) {
+    let system = System::new();
+    system.init_logger();
+    system.mint_to(ADMIN_ID, 1_000_000_000_000_000);
+    for user in USER_ID {
+        system.mint_to(user, 1_000_000_000_000_000);
+    }
+    let program_space = GTestRemoting::new(system, ADMIN_ID.into());
+
+    let code_id = program_space.system().submit_code(code::WASM_BINARY_OPT);
+
+    let main_contract_id = Factory::new(program_space.clone())
+        .new()
+        .send_recv(code_id, "123")
+        .await
+        .unwrap();
+
+    (program_space, main_contract_id)
+}
+
+/// Deploys a mocked vNFT contract next to the main contract
+fn deploy_vnft(program_space: &GTestRemoting) -> (VnftMock, ActorId) {
+    let vnft = VnftMock::default();
+    let program = Program::mock(program_space.system(), vnft.clone());
+    program.send_bytes(ADMIN_ID, []);
+    program_space.run_next_block();
+    (vnft, program.id())
+}
+
+fn token_metadata(name: &str) -> TokenMetadata {
+    TokenMetadata {
+        name: name.to_string(),
+        description: "token_description".to_string(),
+        media: "token_media".to_string(),
+        reference: "token_reference".to_string(),
+    }
+}
+
+/// Client sending its messages from `actor`
+fn client_as(program_space: &GTestRemoting, actor: u64) -> MainClient<GTestRemoting> {
+    MainClient::new(program_space.clone().with_actor_id(actor.into()))
+}
+
+/// Selects `token_ids` of `nft_contract` for `user`
+async fn select(
+    program_space: &GTestRemoting,
+    main_contract_id: ActorId,
+    user: u64,
+    nft_contract: ActorId,
+    token_ids: &[u64],
+) {
+    let selected_nfts = token_ids
+        .iter()
+        .map(|token_id| (nft_contract, U256::from(*token_id)))
+        .collect();
+    client_as(program_space, user)
+        .nfts_selected_by_user(selected_nfts)
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+}
+
+/// Asserts the call panicked with a message containing `expected`, e.g. a `MainError` name
+fn assert_panics_with<T: std::fmt::Debug>(res: Result<T, Error>, expected: &str) {
+    match res {
+        Err(Error::Rtl(RtlError::ReplyHasError(_, payload))) => {
+            let message = String::from_utf8_lossy(&payload);
+            assert!(message.contains(expected), "unexpected panic: {message}");
+        }
+        other => panic!("expected a `{expected}` panic, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_full_snapshot() {
+    let (program_space, main_contract_id) = deploy().await;
+    let client = MainClient::new(program_space.clone());
+    let nft_contract = ActorId::from(100);
+
+    select(&program_space, main_contract_id, USER_ID[0], nft_contract, &[1, 2]).await;
+    select(&program_space, main_contract_id, USER_ID[1], nft_contract, &[3]).await;
+    client_as(&program_space, USER_ID[0])
+        .add_key(vec![1; 32], None)
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+
+    let snapshot = client.query_full_snapshot().recv(main_contract_id).await.unwrap();
+    let version = client.version().recv(main_contract_id).await.unwrap();
+    assert_eq!(snapshot.state.state_version, version);
+    assert_eq!(snapshot.state.user_nft_selections.len(), 2);
+    assert_eq!(snapshot.state.keyring.len(), 1);
+
+    let counts = snapshot.map_key_counts;
+    assert_eq!(counts.user_nft_selections, 2);
+    assert_eq!(counts.last_selection_block, 2);
+    assert_eq!(counts.last_active_block, 2);
+    assert_eq!(counts.keyring, 1);
+    assert_eq!(counts.admin_added_by, 1);
+    assert_eq!(counts.active_runs, 0);
+    assert_eq!(counts.finished_runs, 0);
+    assert_eq!(counts.run_history, 0);
+}
+
+#[tokio::test]
+async fn test_admin_reset_user() {
+    let (program_space, main_contract_id) = deploy().await;
+    let mut client = MainClient::new(program_space.clone());
+    let mut user_client = client_as(&program_space, USER_ID[0]);
+    let user = ActorId::from(USER_ID[0]);
+
+    select(&program_space, main_contract_id, USER_ID[0], ActorId::from(100), &[1]).await;
+    user_client
+        .set_new_selected_weapon(U256::from(7))
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    user_client
+        .add_key(vec![1; 32], None)
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+
+    let res = user_client.admin_reset_user(user).send_recv(main_contract_id).await;
+    assert_panics_with(res, "Only admins");
+
+    client.admin_reset_user(user).send_recv(main_contract_id).await.unwrap();
+
+    let profile = client.query_user_profile(user).recv(main_contract_id).await.unwrap();
+    assert!(profile.selected_nfts.is_empty());
+    assert!(profile.current_weapon.is_none());
+    assert!(profile.last_run_stats.is_none());
+    assert!(profile.has_key);
+
+    // The selection cooldown is cleared along with the selection
+    client.set_selection_cooldown(100).send_recv(main_contract_id).await.unwrap();
+    select(&program_space, main_contract_id, USER_ID[0], ActorId::from(100), &[2]).await;
+}
+
+#[tokio::test]
+async fn test_unauthorized_attempt() {
+    let (program_space, main_contract_id) = deploy().await;
+    let mut listener = service::events::listener(program_space.clone());
+    let mut events = listener.listen().await.unwrap();
+    let mut user_client = client_as(&program_space, USER_ID[0]);
+
+    let res = user_client
+        .add_admin(USER_ID[1].into())
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    assert_eq!(res, Err(MainError::Unauthorized));
+
+    let (source, event) = events.next().await.unwrap();
+    assert_eq!(source, main_contract_id);
+    assert_eq!(
+        event,
+        MainEvent::UnauthorizedAttempt {
+            caller: USER_ID[0].into(),
+            action: "add_admin".to_string(),
+            seq: 1,
+        }
+    );
+    let admins = user_client.query_admins().recv(main_contract_id).await.unwrap();
+    assert_eq!(admins, vec![ActorId::from(ADMIN_ID)]);
+}
+
+#[tokio::test]
+async fn test_hash_algo() {
+    let (program_space, main_contract_id) = deploy().await;
+    let mut client = MainClient::new(program_space.clone());
+    let nft_contract = ActorId::from(100);
+    select(&program_space, main_contract_id, USER_ID[0], nft_contract, &[1]).await;
+    let participants = vec![(ActorId::from(USER_ID[0]), vec![(nft_contract, U256::from(1))])];
+
+    let res = client_as(&program_space, USER_ID[0])
+        .set_hash_algo(HashAlgo::Keccak)
+        .send_recv(main_contract_id)
+        .await;
+    assert_panics_with(res, "Only admins");
+
+    let blake2b = client
+        .preview_run_signature(participants.clone())
+        .recv(main_contract_id)
+        .await
+        .unwrap();
+    client.set_hash_algo(HashAlgo::Keccak).send_recv(main_contract_id).await.unwrap();
+    let keccak = client
+        .preview_run_signature(participants)
+        .recv(main_contract_id)
+        .await
+        .unwrap();
+    assert_ne!(blake2b, keccak);
+
+    let MainEvent::RunStarted { signature, .. } =
+        client.start_run().send_recv(main_contract_id).await.unwrap()
+    else {
+        panic!("expected RunStarted");
+    };
+    assert_eq!(signature, keccak);
+}
+
+#[tokio::test]
+async fn test_allowed_contracts() {
+    let (program_space, main_contract_id) = deploy().await;
+    let mut client = MainClient::new(program_space.clone());
+    let mut user_client = client_as(&program_space, USER_ID[0]);
+    let allowed = ActorId::from(100);
+    let other = ActorId::from(200);
+
+    let res = user_client.add_allowed_contract(allowed).send_recv(main_contract_id).await;
+    assert_panics_with(res, "Only admins");
+
+    // Without an allowlist any contract can be selected
+    select(&program_space, main_contract_id, USER_ID[0], other, &[1]).await;
+
+    client.add_allowed_contract(allowed).send_recv(main_contract_id).await.unwrap();
+    let res = user_client
+        .nfts_selected_by_user(vec![(allowed, 1.into()), (other, 2.into())])
+        .send_recv(main_contract_id)
+        .await;
+    assert_panics_with(res, "ContractNotAllowed");
+    select(&program_space, main_contract_id, USER_ID[0], allowed, &[1]).await;
+
+    client.remove_allowed_contract(allowed).send_recv(main_contract_id).await.unwrap();
+    select(&program_space, main_contract_id, USER_ID[0], other, &[2]).await;
+}
+
+#[tokio::test]
+async fn test_user_selections_page() {
+    let (program_space, main_contract_id) = deploy().await;
+    let client = MainClient::new(program_space.clone());
+    for (i, user) in USER_ID.into_iter().enumerate() {
+        select(&program_space, main_contract_id, user, ActorId::from(100), &[i as u64]).await;
+    }
+
+    let (page, total) = client
+        .query_user_selections_page(0, 2)
+        .recv(main_contract_id)
+        .await
+        .unwrap();
+    assert_eq!(total, 3);
+    let users: Vec<ActorId> = page.iter().map(|selection| selection.user).collect();
+    assert_eq!(users, vec![ActorId::from(USER_ID[0]), ActorId::from(USER_ID[1])]);
+
+    let (page, total) = client
+        .query_user_selections_page(2, 2)
+        .recv(main_contract_id)
+        .await
+        .unwrap();
+    assert_eq!(total, 3);
+    assert_eq!(page.len(), 1);
+    assert_eq!(page[0].user, ActorId::from(USER_ID[2]));
+    assert_eq!(page[0].selected_nfts, vec![(ActorId::from(100), U256::from(2))]);
+
+    let (page, total) = client
+        .query_user_selections_page(5, 2)
+        .recv(main_contract_id)
+        .await
+        .unwrap();
+    assert!(page.is_empty());
+    assert_eq!(total, 3);
+}
+
+/// Starts a run with `users` from the admin account and returns its id
+async fn start_run_with(
+    program_space: &GTestRemoting,
+    main_contract_id: ActorId,
+    users: &[u64],
+) -> u64 {
+    let participants = users.iter().map(|user| ActorId::from(*user)).collect();
+    let event = MainClient::new(program_space.clone())
+        .start_run_with(participants)
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    let MainEvent::RunStarted { run_id, .. } = event else {
+        panic!("expected RunStarted, got {event:?}");
+    };
+    run_id
+}
+
+/// Submits `user`'s stats for the run
+async fn finish_run(
+    program_space: &GTestRemoting,
+    main_contract_id: ActorId,
+    user: u64,
+    run_id: u64,
+    monsters_defeated: u32,
+) -> Result<MainEvent, Error> {
+    client_as(program_space, user)
+        .finish_run(run_id, monsters_defeated, vec![], vec![], 1_000)
+        .send_recv(main_contract_id)
+        .await
+}
+
+#[tokio::test]
+async fn test_finish_run_participants() {
+    let (program_space, main_contract_id) = deploy().await;
+    let client = MainClient::new(program_space.clone());
+    select(&program_space, main_contract_id, USER_ID[0], ActorId::from(100), &[1]).await;
+    select(&program_space, main_contract_id, USER_ID[1], ActorId::from(100), &[2]).await;
+    let run_id = start_run_with(&program_space, main_contract_id, &USER_ID[..2]).await;
+
+    // Selecting after the start doesn't make a user a participant
+    select(&program_space, main_contract_id, USER_ID[2], ActorId::from(100), &[3]).await;
+    let res = finish_run(&program_space, main_contract_id, USER_ID[2], run_id, 5).await;
+    assert_panics_with(res, "NotAParticipant");
+
+    finish_run(&program_space, main_contract_id, USER_ID[0], run_id, 5).await.unwrap();
+    let res = finish_run(&program_space, main_contract_id, USER_ID[0], run_id, 50).await;
+    assert_panics_with(res, "RunAlreadyFinished");
+    assert!(client.query_run(run_id).recv(main_contract_id).await.unwrap().is_some());
+
+    // The last submission closes the run
+    finish_run(&program_space, main_contract_id, USER_ID[1], run_id, 7).await.unwrap();
+    assert!(client.query_run(run_id).recv(main_contract_id).await.unwrap().is_none());
+    let snapshot = client.query_full_snapshot().recv(main_contract_id).await.unwrap();
+    assert_eq!(snapshot.map_key_counts.active_runs, 0);
+    assert_eq!(snapshot.map_key_counts.finished_runs, 0);
+
+    let profile = client
+        .query_user_profile(USER_ID[0].into())
+        .recv(main_contract_id)
+        .await
+        .unwrap();
+    assert_eq!(profile.last_run_stats.unwrap().monsters_defeated, 5);
+}
+
+#[tokio::test]
+async fn test_stats() {
+    let (program_space, main_contract_id) = deploy().await;
+    let mut client = MainClient::new(program_space.clone());
+    client.add_admin(USER_ID[2].into()).send_recv(main_contract_id).await.unwrap().unwrap();
+    select(&program_space, main_contract_id, USER_ID[0], ActorId::from(100), &[1, 2]).await;
+    select(&program_space, main_contract_id, USER_ID[1], ActorId::from(200), &[1]).await;
+    let run_id = start_run_with(&program_space, main_contract_id, &[USER_ID[0]]).await;
+    finish_run(&program_space, main_contract_id, USER_ID[0], run_id, 5).await.unwrap();
+
+    let stats = client.query_stats().recv(main_contract_id).await.unwrap();
+    assert_eq!(
+        stats,
+        GameStats {
+            users_with_selections: 2,
+            admins: 2,
+            runs_finished: 1,
+            nfts_selected: 3,
+        }
+    );
+}
+
+#[tokio::test]
+async fn test_weapon_debounce() {
+    let (program_space, main_contract_id) = deploy().await;
+    let mut listener = service::events::listener(program_space.clone());
+    let mut events = listener.listen().await.unwrap();
+    let mut user_client = client_as(&program_space, USER_ID[0]);
+
+    for _ in 0..2 {
+        user_client
+            .set_new_selected_weapon(7.into())
+            .send_recv(main_contract_id)
+            .await
+            .unwrap();
+    }
+    // A different weapon marks the end of the events to look at
+    user_client
+        .set_new_selected_weapon(8.into())
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+
+    let mut selected = Vec::new();
+    while selected.last() != Some(&U256::from(8)) {
+        let (_, event) = events.next().await.unwrap();
+        if let MainEvent::NewWeaponSelected { token_id, .. } = event {
+            selected.push(token_id);
+        }
+    }
+    assert_eq!(selected, vec![U256::from(7), U256::from(8)]);
+}
+
+#[tokio::test]
+async fn test_silent_mode() {
+    let (program_space, main_contract_id) = deploy().await;
+    let mut listener = service::events::listener(program_space.clone());
+    let mut events = listener.listen().await.unwrap();
+    let mut client = MainClient::new(program_space.clone());
+    let mut user_client = client_as(&program_space, USER_ID[0]);
+
+    let res = user_client.set_emit_events(false).send_recv(main_contract_id).await;
+    assert_panics_with(res, "Only admins");
+
+    let reply = client.set_emit_events(false).send_recv(main_contract_id).await.unwrap();
+    assert_eq!(reply, MainEvent::EmitEventsSet((false, 1)));
+    // Replies still carry the event while nothing is emitted
+    let reply = user_client
+        .set_display_name("silent".to_string())
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    assert!(matches!(reply, MainEvent::DisplayNameSet { seq: 2, .. }));
+    let name = user_client
+        .query_display_name(USER_ID[0].into())
+        .recv(main_contract_id)
+        .await
+        .unwrap();
+    assert_eq!(name.as_deref(), Some("silent"));
+
+    client.set_emit_events(true).send_recv(main_contract_id).await.unwrap();
+    user_client
+        .set_display_name("loud".to_string())
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+
+    // Both toggles are emitted, so the muted seq 2 is the only gap
+    let mut seen = Vec::new();
+    for _ in 0..3 {
+        let (_, event) = events.next().await.unwrap();
+        seen.push(event);
+    }
+    assert_eq!(seen[0], MainEvent::EmitEventsSet((false, 1)));
+    assert_eq!(seen[1], MainEvent::EmitEventsSet((true, 3)));
+    assert!(matches!(seen[2], MainEvent::DisplayNameSet { seq: 4, .. }), "{:?}", seen[2]);
+}
+
+#[tokio::test]
+async fn test_resolve_selection_metadata() {
+    let (program_space, main_contract_id) = deploy().await;
+    let (vnft, vnft_id) = deploy_vnft(&program_space);
+    let user = ActorId::from(USER_ID[0]);
+    let token_ids: Vec<U256> = ["sword", "shield", "helmet"]
+        .into_iter()
+        .map(|name| vnft.mint(user, token_metadata(name)))
+        .collect();
+    let selected_nfts = token_ids.iter().map(|token_id| (vnft_id, *token_id)).collect();
+    let mut user_client = client_as(&program_space, USER_ID[0]);
+    user_client
+        .nfts_selected_by_user(selected_nfts)
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+
+    vnft.burn(token_ids[1]);
+
+    let resolved = user_client
+        .resolve_selection_metadata(user)
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    let names: Vec<(U256, String)> = resolved
+        .into_iter()
+        .map(|(token_id, metadata)| (token_id, metadata.name))
+        .collect();
+    assert_eq!(
+        names,
+        vec![
+            (token_ids[0], "sword".to_string()),
+            (token_ids[2], "helmet".to_string()),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_survival_time_unit() {
+    let (program_space, main_contract_id) = deploy().await;
+    let mut client = MainClient::new(program_space.clone());
+    select(&program_space, main_contract_id, USER_ID[0], ActorId::from(100), &[1]).await;
+    let run_id = start_run_with(&program_space, main_contract_id, &[USER_ID[0]]).await;
+
+    let res = client_as(&program_space, USER_ID[0])
+        .set_survival_time_unit(TimeUnit::Seconds)
+        .send_recv(main_contract_id)
+        .await;
+    assert_panics_with(res, "Only admins");
+
+    client
+        .set_survival_time_unit(TimeUnit::Seconds)
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    let mut user_client = client_as(&program_space, USER_ID[0]);
+    let res = user_client
+        .finish_run(run_id, 1, vec![], vec![], 86_401)
+        .send_recv(main_contract_id)
+        .await;
+    assert_panics_with(res, "SurvivalTimeTooHigh");
+    user_client
+        .finish_run(run_id, 1, vec![], vec![], 86_400)
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+
+    client
+        .set_survival_time_unit(TimeUnit::Blocks)
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    let check = |survival_time| client.validate_run_stats(1, 0, 0, survival_time);
+    assert_eq!(check(28_800).recv(main_contract_id).await.unwrap(), Ok(()));
+    assert_eq!(
+        check(28_801).recv(main_contract_id).await.unwrap(),
+        Err(MainError::SurvivalTimeTooHigh)
+    );
+}
+
+#[tokio::test]
+async fn test_abort_run() {
+    let (program_space, main_contract_id) = deploy().await;
+    let mut client = MainClient::new(program_space.clone());
+    select(&program_space, main_contract_id, USER_ID[0], ActorId::from(100), &[1]).await;
+    select(&program_space, main_contract_id, USER_ID[1], ActorId::from(100), &[2]).await;
+    let run_id = start_run_with(&program_space, main_contract_id, &USER_ID[..2]).await;
+    finish_run(&program_space, main_contract_id, USER_ID[0], run_id, 5).await.unwrap();
+
+    let res = client_as(&program_space, USER_ID[1])
+        .abort_run(run_id)
+        .send_recv(main_contract_id)
+        .await;
+    assert_panics_with(res, "Unauthorized");
+
+    let event = client.abort_run(run_id).send_recv(main_contract_id).await.unwrap();
+    assert!(matches!(event, MainEvent::RunAborted { by, .. } if by == ADMIN_ID.into()));
+    assert!(client.query_run(run_id).recv(main_contract_id).await.unwrap().is_none());
+    let snapshot = client.query_full_snapshot().recv(main_contract_id).await.unwrap();
+    assert_eq!(snapshot.map_key_counts.finished_runs, 0);
+
+    let res = finish_run(&program_space, main_contract_id, USER_ID[1], run_id, 5).await;
+    assert_panics_with(res, "RunAlreadyFinished");
+    let res = client.abort_run(run_id).send_recv(main_contract_id).await;
+    assert_panics_with(res, "RunAlreadyFinished");
+    let res = client.abort_run(run_id + 1).send_recv(main_contract_id).await;
+    assert_panics_with(res, "RunNotFound");
+
+    // The initiator may abort their own run
+    let mut user_client = client_as(&program_space, USER_ID[1]);
+    let event = user_client
+        .start_run_with(vec![USER_ID[1].into()])
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    let MainEvent::RunStarted { run_id, .. } = event else {
+        panic!("expected RunStarted, got {event:?}");
+    };
+    user_client.abort_run(run_id).send_recv(main_contract_id).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_total_runs_started() {
+    let (program_space, main_contract_id) = deploy().await;
+    let mut client = MainClient::new(program_space.clone());
+    let total = client.query_total_runs_started().recv(main_contract_id).await.unwrap();
+    assert_eq!(total, 0);
+
+    select(&program_space, main_contract_id, USER_ID[0], ActorId::from(100), &[1]).await;
+    let first = start_run_with(&program_space, main_contract_id, &[USER_ID[0]]).await;
+    let second = start_run_with(&program_space, main_contract_id, &[USER_ID[0]]).await;
+    assert_eq!((first, second), (0, 1));
+    // Aborted runs still count as started
+    client.abort_run(first).send_recv(main_contract_id).await.unwrap();
+
+    let total = client.query_total_runs_started().recv(main_contract_id).await.unwrap();
+    assert_eq!(total, 2);
+}
+
+#[tokio::test]
+async fn test_has_key() {
+    let (program_space, main_contract_id) = deploy().await;
+    let mut user_client = client_as(&program_space, USER_ID[0]);
+    let user = ActorId::from(USER_ID[0]);
+    assert!(!user_client.has_key(user).recv(main_contract_id).await.unwrap());
+
+    user_client
+        .add_key(vec![1; 32], Some("laptop".to_string()))
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    assert!(user_client.has_key(user).recv(main_contract_id).await.unwrap());
+    assert!(!user_client.has_key(USER_ID[1].into()).recv(main_contract_id).await.unwrap());
+
+    user_client.remove_key().send_recv(main_contract_id).await.unwrap();
+    assert!(!user_client.has_key(user).recv(main_contract_id).await.unwrap());
+}
+
+#[tokio::test]
+async fn test_finish_run_awarded_token_ids() {
+    let (program_space, main_contract_id) = deploy().await;
+    let (vnft, vnft_id) = deploy_vnft(&program_space);
+    let mut client = MainClient::new(program_space.clone());
+    let user = ActorId::from(USER_ID[0]);
+    select(&program_space, main_contract_id, USER_ID[0], ActorId::from(100), &[1]).await;
+    let mut user_client = client_as(&program_space, USER_ID[0]);
+
+    // Minting is disabled until a reward contract is set
+    let run_id = start_run_with(&program_space, main_contract_id, &[USER_ID[0]]).await;
+    let event = user_client
+        .finish_run(run_id, 1, vec![], vec![3.into()], 1_000)
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    assert!(matches!(
+        event,
+        MainEvent::RunFinished { awarded_token_ids, .. } if awarded_token_ids.is_empty()
+    ));
+    assert!(vnft.0.borrow().tokens.is_empty());
+
+    client
+        .set_reward_contract(Some(vnft_id))
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    let run_id = start_run_with(&program_space, main_contract_id, &[USER_ID[0]]).await;
+    let event = user_client
+        .finish_run(run_id, 1, vec![], vec![3.into(), 4.into()], 1_000)
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    let MainEvent::RunFinished { awarded_token_ids, .. } = event else {
+        panic!("expected RunFinished, got {event:?}");
+    };
+
+    let state = vnft.0.borrow();
+    let minted: Vec<U256> = state.tokens.keys().copied().collect();
+    assert_eq!(awarded_token_ids, minted);
+    let names: Vec<&str> = state
+        .tokens
+        .values()
+        .map(|(owner, metadata)| {
+            assert_eq!(*owner, user);
+            metadata.name.as_str()
+        })
+        .collect();
+    assert_eq!(names, vec!["Item #3", "Item #4"]);
+}
+
+#[tokio::test]
+async fn test_prune_run_stats() {
+    let (program_space, main_contract_id) = deploy().await;
+    let mut client = MainClient::new(program_space.clone());
+    select(&program_space, main_contract_id, USER_ID[0], ActorId::from(100), &[1]).await;
+    select(&program_space, main_contract_id, USER_ID[1], ActorId::from(100), &[2]).await;
+    let run_id = start_run_with(&program_space, main_contract_id, &USER_ID[..2]).await;
+    finish_run(&program_space, main_contract_id, USER_ID[0], run_id, 5).await.unwrap();
+    finish_run(&program_space, main_contract_id, USER_ID[1], run_id, 7).await.unwrap();
+    let last_block = client
+        .query_user_profile(USER_ID[1].into())
+        .recv(main_contract_id)
+        .await
+        .unwrap()
+        .last_run_stats
+        .unwrap()
+        .block_height;
+
+    let res = client_as(&program_space, USER_ID[0])
+        .admin_prune_run_stats(last_block)
+        .send_recv(main_contract_id)
+        .await;
+    assert_panics_with(res, "Only admins");
+
+    let event = client
+        .admin_prune_run_stats(last_block)
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    assert!(matches!(event, MainEvent::RunStatsPruned { removed: 1, .. }), "{event:?}");
+
+    let profile = client
+        .query_user_profile(USER_ID[0].into())
+        .recv(main_contract_id)
+        .await
+        .unwrap();
+    assert!(profile.last_run_stats.is_none());
+    let runs = client.query_runs_in_range(0, u32::MAX, 0).recv(main_contract_id).await.unwrap();
+    assert_eq!(runs.len(), 1);
+    assert_eq!(runs[0].user, ActorId::from(USER_ID[1]));
+}
+
+fn run_stats(user: ActorId, monsters_defeated: u32) -> RunStats {
+    RunStats {
+        user,
+        monsters_defeated,
+        items_found: vec![],
+        new_items_selected: vec![],
+        survival_time: 1_000,
+        block_height: 0,
+        initiator: user,
+        run_id: 0,
+    }
+}
+
+#[tokio::test]
+async fn test_run_initiator() {
+    let (program_space, main_contract_id) = deploy().await;
+    let client = MainClient::new(program_space.clone());
+    select(&program_space, main_contract_id, USER_ID[0], ActorId::from(100), &[1]).await;
+    select(&program_space, main_contract_id, USER_ID[1], ActorId::from(100), &[2]).await;
+    let mut user_client = client_as(&program_space, USER_ID[0]);
+
+    let forged = run_stats(USER_ID[2].into(), 5);
+    let res = user_client.run_finished(0, forged.clone()).send_recv(main_contract_id).await;
+    assert_panics_with(res, "RunNotFound");
+
+    // USER_ID[1] starts the run, the admin is never the initiator
+    let event = client_as(&program_space, USER_ID[1])
+        .start_run_with(vec![USER_ID[0].into(), USER_ID[1].into()])
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    let MainEvent::RunStarted { initiator, run_id, .. } = event else {
+        panic!("expected RunStarted, got {event:?}");
+    };
+    assert_eq!(initiator, ActorId::from(USER_ID[1]));
+
+    let result = user_client
+        .run_finished(run_id, forged)
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    assert_eq!(result.stats.user, ActorId::from(USER_ID[0]));
+    assert_eq!(result.stats.initiator, initiator);
+
+    let event = finish_run(&program_space, main_contract_id, USER_ID[1], run_id, 7).await.unwrap();
+    assert!(matches!(event, MainEvent::RunFinished { initiator: by, .. } if by == initiator));
+    let state = client.query_state().recv(main_contract_id).await.unwrap();
+    assert!(state.last_run_stats.iter().all(|stats| stats.initiator == initiator));
+}
+
+#[tokio::test]
+async fn test_event_seq() {
+    let (program_space, main_contract_id) = deploy().await;
+    let mut listener = service::events::listener(program_space.clone());
+    let mut events = listener.listen().await.unwrap();
+    let mut user_client = client_as(&program_space, USER_ID[0]);
+    let user = ActorId::from(USER_ID[0]);
+
+    // Nothing to cancel and re-selecting the same weapon are no-ops
+    let reply = user_client.cancel_selection().send_recv(main_contract_id).await.unwrap();
+    assert_eq!(reply, MainEvent::SelectionCancelled { user, seq: 0 });
+    let reply = user_client
+        .set_new_selected_weapon(7.into())
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    assert_eq!(reply, MainEvent::NewWeaponSelected { user, token_id: 7.into(), seq: 1 });
+    let reply = user_client
+        .set_new_selected_weapon(7.into())
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    assert_eq!(reply, MainEvent::NewWeaponSelected { user, token_id: 7.into(), seq: 0 });
+
+    select(&program_space, main_contract_id, USER_ID[0], ActorId::from(100), &[1]).await;
+    let reply = user_client.cancel_selection().send_recv(main_contract_id).await.unwrap();
+    assert_eq!(reply, MainEvent::SelectionCancelled { user, seq: 3 });
+
+    // Only real events were emitted, numbered without gaps
+    let mut seqs = Vec::new();
+    for _ in 0..3 {
+        let (_, event) = events.next().await.unwrap();
+        seqs.push(match event {
+            MainEvent::NewWeaponSelected { seq, .. }
+            | MainEvent::NFTsSelected { seq, .. }
+            | MainEvent::SelectionCancelled { seq, .. } => seq,
+            other => panic!("unexpected event {other:?}"),
+        });
+    }
+    assert_eq!(seqs, vec![1, 2, 3]);
+}
+
+#[tokio::test]
+async fn test_finish_run_without_run() {
+    let (program_space, main_contract_id) = deploy().await;
+
+    let res = finish_run(&program_space, main_contract_id, USER_ID[0], 0, 5).await;
+    assert_panics_with(res, "NoActiveRun");
+
+    select(&program_space, main_contract_id, USER_ID[0], ActorId::from(100), &[1]).await;
+    let res = finish_run(&program_space, main_contract_id, USER_ID[0], 0, 5).await;
+    assert_panics_with(res, "RunNotFound");
+
+    let run_id = start_run_with(&program_space, main_contract_id, &[USER_ID[0]]).await;
+    // Cancelling the selection unties the user from the run's gameplay
+    client_as(&program_space, USER_ID[0])
+        .cancel_selection()
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    let res = finish_run(&program_space, main_contract_id, USER_ID[0], run_id, 5).await;
+    assert_panics_with(res, "NoActiveRun");
+}
+
+#[tokio::test]
+async fn test_max_item_id() {
+    let (program_space, main_contract_id) = deploy().await;
+    let mut client = MainClient::new(program_space.clone());
+    select(&program_space, main_contract_id, USER_ID[0], ActorId::from(100), &[1]).await;
+    let run_id = start_run_with(&program_space, main_contract_id, &[USER_ID[0]]).await;
+    let mut user_client = client_as(&program_space, USER_ID[0]);
+
+    let res = user_client.set_max_item_id(50.into()).send_recv(main_contract_id).await;
+    assert_panics_with(res, "Only admins");
+    client.set_max_item_id(50.into()).send_recv(main_contract_id).await.unwrap();
+
+    let res = user_client
+        .finish_run(run_id, 1, vec![51.into()], vec![], 1_000)
+        .send_recv(main_contract_id)
+        .await;
+    assert_panics_with(res, "ItemIdOutOfRange");
+    let res = user_client
+        .finish_run(run_id, 1, vec![], vec![51.into()], 1_000)
+        .send_recv(main_contract_id)
+        .await;
+    assert_panics_with(res, "ItemIdOutOfRange");
+
+    user_client
+        .finish_run(run_id, 1, vec![50.into()], vec![0.into()], 1_000)
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_validate_selection() {
+    let (program_space, main_contract_id) = deploy().await;
+    let mut client = MainClient::new(program_space.clone());
+    let user_client = client_as(&program_space, USER_ID[0]);
+    let nft_contract = ActorId::from(100);
+    let validate = |selected_nfts: Vec<(ActorId, U256)>| {
+        user_client.validate_selection(selected_nfts).recv(main_contract_id)
+    };
+
+    assert_eq!(validate(vec![(nft_contract, 1.into())]).await.unwrap(), Ok(()));
+    assert_eq!(validate(vec![]).await.unwrap(), Err(MainError::EmptySelection));
+    assert_eq!(
+        validate(vec![(nft_contract, 1.into()), (nft_contract, 1.into())]).await.unwrap(),
+        Err(MainError::DuplicateSelection)
+    );
+    // Nothing is stored by the dry run
+    let nfts = user_client.query_user_nfts(USER_ID[0].into()).recv(main_contract_id).await;
+    assert_eq!(nfts.unwrap(), None);
+
+    client.add_allowed_contract(nft_contract).send_recv(main_contract_id).await.unwrap();
+    assert_eq!(
+        validate(vec![(ActorId::from(200), 1.into())]).await.unwrap(),
+        Err(MainError::ContractNotAllowed)
+    );
+
+    client.set_selection_cooldown(100).send_recv(main_contract_id).await.unwrap();
+    select(&program_space, main_contract_id, USER_ID[0], nft_contract, &[1]).await;
+    assert_eq!(
+        validate(vec![(nft_contract, 2.into())]).await.unwrap(),
+        Err(MainError::SelectionCooldown)
+    );
+}
+
+#[tokio::test]
+async fn test_admin_award_items() {
+    let (program_space, main_contract_id) = deploy().await;
+    let (vnft, vnft_id) = deploy_vnft(&program_space);
+    vnft.0.borrow_mut().allowed_schemes = vec!["ipfs://".to_string()];
+    let mut client = MainClient::new(program_space.clone());
+    let user = ActorId::from(USER_ID[0]);
+    select(&program_space, main_contract_id, USER_ID[0], ActorId::from(100), &[1]).await;
+    let run_id = start_run_with(&program_space, main_contract_id, &[USER_ID[0]]).await;
+    let item_ids: Vec<U256> = (1..=5).map(U256::from).collect();
+    client_as(&program_space, USER_ID[0])
+        .finish_run(run_id, 1, vec![], item_ids, 1_000)
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+
+    let res = client_as(&program_space, USER_ID[0])
+        .admin_award_items(vnft_id, run_id, user)
+        .send_recv(main_contract_id)
+        .await;
+    assert_panics_with(res, "Only admins");
+    let res = client
+        .admin_award_items(vnft_id, run_id + 1, user)
+        .send_recv(main_contract_id)
+        .await;
+    assert_eq!(res.unwrap(), Err(MainError::RunNotFound));
+
+    // Empty media is rejected by the vNFT, the failed award can be retried
+    let res = client.admin_award_items(vnft_id, run_id, user).send_recv(main_contract_id).await;
+    assert_eq!(res.unwrap(), Err(MainError::VnftCallFailed));
+    assert!(vnft.0.borrow().tokens.is_empty());
+
+    client
+        .set_reward_media_base("ipfs://items/".to_string())
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    let token_ids = client
+        .admin_award_items(vnft_id, run_id, user)
+        .send_recv(main_contract_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(token_ids.len(), 5);
+    {
+        let state = vnft.0.borrow();
+        assert_eq!(state.mint_batch_calls, 1);
+        assert_eq!(state.tokens.keys().copied().collect::<Vec<_>>(), token_ids);
+        let media: Vec<&str> = state.tokens.values().map(|(_, m)| m.media.as_str()).collect();
+        assert_eq!(media[0], "ipfs://items/1");
+        assert_eq!(media[4], "ipfs://items/5");
+    }
+    let awarded = client.query_awarded_rewards(run_id, user).recv(main_contract_id).await.unwrap();
+    assert_eq!(awarded, token_ids);
+
+    let res = client.admin_award_items(vnft_id, run_id, user).send_recv(main_contract_id).await;
+    assert_eq!(res.unwrap(), Err(MainError::AlreadyAwarded));
+    assert_eq!(vnft.0.borrow().tokens.len(), 5);
+
+    // Stats minted by `finish_run` count as awarded too
+    client
+        .set_reward_contract(Some(vnft_id))
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    let run_id = start_run_with(&program_space, main_contract_id, &[USER_ID[0]]).await;
+    client_as(&program_space, USER_ID[0])
+        .finish_run(run_id, 1, vec![], vec![6.into()], 1_000)
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    let res = client.admin_award_items(vnft_id, run_id, user).send_recv(main_contract_id).await;
+    assert_eq!(res.unwrap(), Err(MainError::AlreadyAwarded));
+    assert_eq!(vnft.0.borrow().mint_batch_calls, 2);
+}
+
+#[tokio::test]
+async fn test_preview_run_signature() {
+    let (program_space, main_contract_id) = deploy().await;
+    let nft_contract = ActorId::from(100);
+    select(&program_space, main_contract_id, USER_ID[0], nft_contract, &[1, 2]).await;
+    select(&program_space, main_contract_id, USER_ID[1], nft_contract, &[3]).await;
+    let mut user_client = client_as(&program_space, USER_ID[0]);
+    let participants = vec![
+        (ActorId::from(USER_ID[1]), vec![(nft_contract, U256::from(3))]),
+        (
+            ActorId::from(USER_ID[0]),
+            vec![(nft_contract, U256::from(1)), (nft_contract, U256::from(2))],
+        ),
+    ];
+
+    let preview = user_client
+        .preview_run_signature(participants.clone())
+        .recv(main_contract_id)
+        .await
+        .unwrap();
+    // The preview doesn't start a run
+    let total = user_client.query_total_runs_started().recv(main_contract_id).await.unwrap();
+    assert_eq!(total, 0);
+    // The initiator is part of the signature
+    let admin_preview = MainClient::new(program_space.clone())
+        .preview_run_signature(participants)
+        .recv(main_contract_id)
+        .await
+        .unwrap();
+    assert_ne!(preview, admin_preview);
+
+    let event = user_client
+        .start_run_with(vec![USER_ID[1].into(), USER_ID[0].into()])
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    let MainEvent::RunStarted { signature, .. } = event else {
+        panic!("expected RunStarted, got {event:?}");
+    };
+    assert_eq!(signature, preview);
+}
+
+#[tokio::test]
+async fn test_selection_cooldown() {
+    let (program_space, main_contract_id) = deploy().await;
+    let mut client = MainClient::new(program_space.clone());
+    let nft_contract = ActorId::from(100);
+
+    let res = client_as(&program_space, USER_ID[0])
+        .set_selection_cooldown(5)
+        .send_recv(main_contract_id)
+        .await;
+    assert_panics_with(res, "Only admins");
+    client.set_selection_cooldown(5).send_recv(main_contract_id).await.unwrap();
+
+    select(&program_space, main_contract_id, USER_ID[0], nft_contract, &[1]).await;
+    let res = client_as(&program_space, USER_ID[0])
+        .nfts_selected_by_user(vec![(nft_contract, 2.into())])
+        .send_recv(main_contract_id)
+        .await;
+    assert_panics_with(res, "SelectionCooldown");
+    // Other users aren't affected
+    select(&program_space, main_contract_id, USER_ID[1], nft_contract, &[2]).await;
+
+    for _ in 0..5 {
+        program_space.run_next_block();
+    }
+    select(&program_space, main_contract_id, USER_ID[0], nft_contract, &[2]).await;
+}
+
+#[tokio::test]
+async fn test_start_run_with() {
+    let (program_space, main_contract_id) = deploy().await;
+    let mut client = MainClient::new(program_space.clone());
+    select(&program_space, main_contract_id, USER_ID[0], ActorId::from(100), &[1]).await;
+    select(&program_space, main_contract_id, USER_ID[1], ActorId::from(100), &[2]).await;
+
+    let res = client.start_run_with(vec![]).send_recv(main_contract_id).await;
+    assert_panics_with(res, "No participants");
+    let res = client
+        .start_run_with(vec![USER_ID[0].into(), USER_ID[2].into()])
+        .send_recv(main_contract_id)
+        .await;
+    assert_panics_with(res, "NoSelection");
+
+    let event = client
+        .start_run_with(vec![USER_ID[1].into(), USER_ID[0].into(), USER_ID[1].into()])
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    let MainEvent::RunStarted { participants, run_id, .. } = event else {
+        panic!("expected RunStarted, got {event:?}");
+    };
+    let users: Vec<ActorId> = participants.iter().map(|(user, _)| *user).collect();
+    assert_eq!(users, vec![ActorId::from(USER_ID[1]), ActorId::from(USER_ID[0])]);
+    let run = client.query_run(run_id).recv(main_contract_id).await.unwrap().unwrap();
+    assert_eq!(run.participants, users);
+
+    // Users left out of the subset can't submit stats
+    select(&program_space, main_contract_id, USER_ID[2], ActorId::from(100), &[3]).await;
+    let res = finish_run(&program_space, main_contract_id, USER_ID[2], run_id, 5).await;
+    assert_panics_with(res, "NotAParticipant");
+}
+
+#[tokio::test]
+async fn test_run_finished_deltas() {
+    let (program_space, main_contract_id) = deploy().await;
+    let client = MainClient::new(program_space.clone());
+    select(&program_space, main_contract_id, USER_ID[0], ActorId::from(100), &[1]).await;
+    let first_run = start_run_with(&program_space, main_contract_id, &[USER_ID[0]]).await;
+    let mut user_client = client_as(&program_space, USER_ID[0]);
+    let user = ActorId::from(USER_ID[0]);
+
+    let first = user_client
+        .run_finished(first_run, RunStats { survival_time: 5_000, ..run_stats(user, 10) })
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    assert_eq!((first.monsters_defeated_delta, first.survival_time_delta), (0, 0));
+    assert_ne!(first.stats.block_height, 0);
+    assert_eq!(first.stats.run_id, first_run);
+
+    // Repeats are rejected and leave the counters alone
+    let res = user_client
+        .run_finished(first_run, run_stats(user, 99))
+        .send_recv(main_contract_id)
+        .await;
+    assert_panics_with(res, "RunAlreadyFinished");
+    let res = finish_run(&program_space, main_contract_id, USER_ID[0], first_run, 99).await;
+    assert_panics_with(res, "RunAlreadyFinished");
+    let stats = client.query_stats().recv(main_contract_id).await.unwrap();
+    assert_eq!(stats.runs_finished, 1);
+
+    let second_run = start_run_with(&program_space, main_contract_id, &[USER_ID[0]]).await;
+    let second = user_client
+        .run_finished(second_run, RunStats { survival_time: 2_000, ..run_stats(user, 25) })
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    assert_eq!(second.monsters_defeated_delta, 15);
+    assert_eq!(second.survival_time_delta, -3_000);
+    assert_eq!(second.stats.monsters_defeated, 25);
+
+    let res = user_client
+        .run_finished(second_run, run_stats(user, 1_001))
+        .send_recv(main_contract_id)
+        .await;
+    assert_panics_with(res, "TooManyMonstersDefeated");
+}
+
+#[tokio::test]
+async fn test_transfer_admin_role() {
+    let (program_space, main_contract_id) = deploy().await;
+    let mut client = MainClient::new(program_space.clone());
+    let new_admin = ActorId::from(USER_ID[0]);
+    client.add_admin(USER_ID[1].into()).send_recv(main_contract_id).await.unwrap().unwrap();
+
+    client.transfer_admin_role(new_admin).send_recv(main_contract_id).await.unwrap();
+    // Nothing changes until the proposed admin accepts
+    assert!(!client.is_admin(new_admin).recv(main_contract_id).await.unwrap());
+    let res = client_as(&program_space, USER_ID[2])
+        .accept_admin_role()
+        .send_recv(main_contract_id)
+        .await;
+    assert_panics_with(res, "Unauthorized");
+
+    client_as(&program_space, USER_ID[0])
+        .accept_admin_role()
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    let admins = client.query_admins().recv(main_contract_id).await.unwrap();
+    assert_eq!(admins, vec![new_admin]);
+    let adder = client.query_admin_adder(new_admin).recv(main_contract_id).await.unwrap();
+    assert_eq!(adder, Some(ActorId::from(ADMIN_ID)));
+    // Earlier entries are kept as history
+    let adder = client
+        .query_admin_adder(USER_ID[1].into())
+        .recv(main_contract_id)
+        .await
+        .unwrap();
+    assert_eq!(adder, Some(ActorId::from(ADMIN_ID)));
+
+    let res = client.set_max_participants(3).send_recv(main_contract_id).await;
+    assert_panics_with(res, "Only admins");
+}
+
+#[tokio::test]
+async fn test_runs_initiated_and_joined() {
+    let (program_space, main_contract_id) = deploy().await;
+    let client = MainClient::new(program_space.clone());
+    select(&program_space, main_contract_id, USER_ID[0], ActorId::from(100), &[1]).await;
+    select(&program_space, main_contract_id, USER_ID[1], ActorId::from(100), &[2]).await;
+
+    let first = start_run_with(&program_space, main_contract_id, &USER_ID[..2]).await;
+    let event = client_as(&program_space, USER_ID[0])
+        .start_run_with(vec![USER_ID[0].into()])
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    let MainEvent::RunStarted { run_id: second, .. } = event else {
+        panic!("expected RunStarted, got {event:?}");
+    };
+
+    let initiated = |user: u64| client.query_runs_initiated_by(user.into()).recv(main_contract_id);
+    assert_eq!(initiated(ADMIN_ID).await.unwrap(), vec![first]);
+    assert_eq!(initiated(USER_ID[0]).await.unwrap(), vec![second]);
+    assert!(initiated(USER_ID[1]).await.unwrap().is_empty());
+
+    let joined = |user: u64| client.query_runs_joined_by(user.into()).recv(main_contract_id);
+    assert_eq!(joined(USER_ID[0]).await.unwrap(), vec![first, second]);
+    assert_eq!(joined(USER_ID[1]).await.unwrap(), vec![first]);
+    // Starting a run doesn't make the initiator a participant
+    assert!(joined(ADMIN_ID).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_query_state_at() {
+    let (program_space, main_contract_id) = deploy().await;
+    let client = MainClient::new(program_space.clone());
+
+    let (state, first_height) = client.query_state_at().recv(main_contract_id).await.unwrap();
+    assert_ne!(first_height, 0);
+    assert!(state.user_nft_selections.is_empty());
+
+    select(&program_space, main_contract_id, USER_ID[0], ActorId::from(100), &[1]).await;
+    let (state, height) = client.query_state_at().recv(main_contract_id).await.unwrap();
+    assert!(height > first_height);
+    assert_eq!(state.user_nft_selections.len(), 1);
+    // The selection was recorded before the height was read
+    assert!(state.last_selection_block[0].1 <= height);
+}
+
+#[tokio::test]
+async fn test_validate_run_stats() {
+    let (program_space, main_contract_id) = deploy().await;
+    let client = MainClient::new(program_space.clone());
+    let validate = |monsters_defeated, items_found_len, new_items_len, survival_time| {
+        client
+            .validate_run_stats(monsters_defeated, items_found_len, new_items_len, survival_time)
+            .recv(main_contract_id)
+    };
+
+    assert_eq!(validate(1_000, 100, 100, 86_400_000).await.unwrap(), Ok(()));
+    assert_eq!(
+        validate(1_001, 0, 0, 0).await.unwrap(),
+        Err(MainError::TooManyMonstersDefeated)
+    );
+    assert_eq!(validate(0, 101, 0, 0).await.unwrap(), Err(MainError::TooManyItemsFound));
+    assert_eq!(
+        validate(0, 0, 101, 0).await.unwrap(),
+        Err(MainError::TooManyNewItemsSelected)
+    );
+    assert_eq!(
+        validate(0, 0, 0, 86_400_001).await.unwrap(),
+        Err(MainError::SurvivalTimeTooHigh)
+    );
+    // The simulation records nothing
+    let stats = client.query_stats().recv(main_contract_id).await.unwrap();
+    assert_eq!(stats.runs_finished, 0);
+}
+
+#[tokio::test]
+async fn test_display_name() {
+    let (program_space, main_contract_id) = deploy().await;
+    let mut user_client = client_as(&program_space, USER_ID[0]);
+    let user = ActorId::from(USER_ID[0]);
+
+    for name in [String::new(), "x".repeat(33), "tab\tname".to_string()] {
+        let res = user_client.set_display_name(name).send_recv(main_contract_id).await;
+        assert_panics_with(res, "InvalidDisplayName");
+    }
+
+    user_client
+        .set_display_name("x".repeat(32))
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    user_client
+        .set_display_name("Slayer".to_string())
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    let name = user_client.query_display_name(user).recv(main_contract_id).await.unwrap();
+    assert_eq!(name.as_deref(), Some("Slayer"));
+    let profile = user_client.query_user_profile(user).recv(main_contract_id).await.unwrap();
+    assert_eq!(profile.display_name.as_deref(), Some("Slayer"));
+    let other = user_client
+        .query_display_name(USER_ID[1].into())
+        .recv(main_contract_id)
+        .await
+        .unwrap();
+    assert_eq!(other, None);
+}
+
+#[tokio::test]
+async fn test_signature_bytes() {
+    let (program_space, main_contract_id) = deploy().await;
+    select(&program_space, main_contract_id, USER_ID[0], ActorId::from(100), &[1]).await;
+
+    let event = MainClient::new(program_space.clone())
+        .start_run_with(vec![USER_ID[0].into()])
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    let encoded = event.encode();
+    let MainEvent::RunStarted { signature, signature_bytes, .. } = event else {
+        panic!("expected RunStarted, got {event:?}");
+    };
+    assert_eq!(signature_bytes, signature.to_vec());
+    // `signature_bytes` is encoded last, after `seq`
+    assert!(encoded.ends_with(&signature_bytes.encode()));
+}
+
+#[tokio::test]
+async fn test_stale_selections() {
+    let (program_space, main_contract_id) = deploy().await;
+    let (vnft, vnft_id) = deploy_vnft(&program_space);
+    let user = ActorId::from(USER_ID[0]);
+    let sword = vnft.mint(user, token_metadata("sword"));
+    let shield = vnft.mint(user, token_metadata("shield"));
+    let mut user_client = client_as(&program_space, USER_ID[0]);
+    user_client
+        .nfts_selected_by_user(vec![(vnft_id, sword), (vnft_id, shield)])
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+
+    let stale = user_client
+        .query_stale_selections(user)
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    assert!(stale.is_empty());
+
+    vnft.burn(shield);
+    let stale = user_client
+        .query_stale_selections(user)
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    assert_eq!(stale, vec![(vnft_id, shield)]);
+    // Reporting stale tokens leaves the selection untouched
+    let nfts = user_client.query_user_nfts(user).recv(main_contract_id).await.unwrap();
+    assert_eq!(nfts, Some(vec![sword, shield]));
+}
+
+#[tokio::test]
+async fn test_select_and_start() {
+    let (program_space, main_contract_id) = deploy().await;
+    let mut listener = service::events::listener(program_space.clone());
+    let mut events = listener.listen().await.unwrap();
+    select(&program_space, main_contract_id, USER_ID[1], ActorId::from(100), &[2]).await;
+    let mut user_client = client_as(&program_space, USER_ID[0]);
+    let user = ActorId::from(USER_ID[0]);
+    let selected_nfts = vec![(ActorId::from(100), U256::from(1))];
+
+    let res = user_client.select_and_start(vec![]).send_recv(main_contract_id).await;
+    assert_panics_with(res, "EmptySelection");
+    let total = user_client.query_total_runs_started().recv(main_contract_id).await.unwrap();
+    assert_eq!(total, 0);
+
+    let event = user_client
+        .select_and_start(selected_nfts.clone())
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    let MainEvent::RunStarted { initiator, participants, .. } = event else {
+        panic!("expected RunStarted, got {event:?}");
+    };
+    assert_eq!(initiator, user);
+    // Only the caller takes part, even with other selections around
+    assert_eq!(participants, vec![(user, selected_nfts.clone())]);
+
+    let mut emitted = Vec::new();
+    for _ in 0..3 {
+        emitted.push(events.next().await.unwrap().1);
+    }
+    assert!(matches!(&emitted[1], MainEvent::NFTsSelected { user: by, .. } if *by == user));
+    assert!(matches!(&emitted[2], MainEvent::RunStarted { .. }));
+    let nfts = user_client.query_user_nfts_full(user).recv(main_contract_id).await.unwrap();
+    assert_eq!(nfts, selected_nfts);
+}
+
+#[tokio::test]
+async fn test_finish_run_retry() {
+    let (program_space, main_contract_id) = deploy().await;
+    let client = MainClient::new(program_space.clone());
+    select(&program_space, main_contract_id, USER_ID[0], ActorId::from(100), &[1]).await;
+    select(&program_space, main_contract_id, USER_ID[1], ActorId::from(100), &[2]).await;
+    let run_id = start_run_with(&program_space, main_contract_id, &USER_ID[..2]).await;
+
+    finish_run(&program_space, main_contract_id, USER_ID[0], run_id, 5).await.unwrap();
+    let res = finish_run(&program_space, main_contract_id, USER_ID[0], run_id, 500).await;
+    assert_panics_with(res, "RunAlreadyFinished");
+
+    // The first result is kept and counted once
+    let profile = client
+        .query_user_profile(USER_ID[0].into())
+        .recv(main_contract_id)
+        .await
+        .unwrap();
+    assert_eq!(profile.last_run_stats.unwrap().monsters_defeated, 5);
+    let stats = client.query_stats().recv(main_contract_id).await.unwrap();
+    assert_eq!(stats.runs_finished, 1);
+
+    // The same user can still submit to a later run
+    let next_run_id = start_run_with(&program_space, main_contract_id, &[USER_ID[0]]).await;
+    finish_run(&program_space, main_contract_id, USER_ID[0], next_run_id, 9).await.unwrap();
+    let stats = client.query_stats().recv(main_contract_id).await.unwrap();
+    assert_eq!(stats.runs_finished, 2);
+}
+
+#[tokio::test]
+async fn test_admin_count() {
+    let (program_space, main_contract_id) = deploy().await;
+    let mut client = MainClient::new(program_space.clone());
+    assert_eq!(client.admin_count().recv(main_contract_id).await.unwrap(), 1);
+    assert!(client.is_admin(ADMIN_ID.into()).recv(main_contract_id).await.unwrap());
+
+    client.add_admin(USER_ID[0].into()).send_recv(main_contract_id).await.unwrap().unwrap();
+    client.add_admin(USER_ID[1].into()).send_recv(main_contract_id).await.unwrap().unwrap();
+    // Adding an existing admin doesn't count twice
+    client.add_admin(USER_ID[1].into()).send_recv(main_contract_id).await.unwrap().unwrap();
+    assert_eq!(client.admin_count().recv(main_contract_id).await.unwrap(), 3);
+    assert!(client.is_admin(USER_ID[1].into()).recv(main_contract_id).await.unwrap());
+    assert!(!client.is_admin(USER_ID[2].into()).recv(main_contract_id).await.unwrap());
+
+    // Handing the role over removes every other admin
+    client.transfer_admin_role(USER_ID[2].into()).send_recv(main_contract_id).await.unwrap();
+    client_as(&program_space, USER_ID[2])
+        .accept_admin_role()
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    assert_eq!(client.admin_count().recv(main_contract_id).await.unwrap(), 1);
+    assert!(!client.is_admin(ADMIN_ID.into()).recv(main_contract_id).await.unwrap());
+    assert!(client.is_admin(USER_ID[2].into()).recv(main_contract_id).await.unwrap());
+}
+
+#[tokio::test]
+async fn test_delegated_selection() {
+    let (program_space, main_contract_id) = deploy().await;
+    let user = ActorId::from(USER_ID[0]);
+    let delegate = ActorId::from(USER_ID[1]);
+    let selected_nfts = vec![(ActorId::from(100), U256::from(1))];
+    let mut user_client = client_as(&program_space, USER_ID[0]);
+    let mut delegate_client = client_as(&program_space, USER_ID[1]);
+
+    let res = delegate_client
+        .nfts_selected_for(user, selected_nfts.clone())
+        .send_recv(main_contract_id)
+        .await;
+    assert_panics_with(res, "Unauthorized");
+
+    user_client.authorize_delegate(delegate).send_recv(main_contract_id).await.unwrap();
+    let event = delegate_client
+        .nfts_selected_for(user, selected_nfts.clone())
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    let MainEvent::NFTsSelectedFor { user: target, delegate: by, .. } = event else {
+        panic!("expected NFTsSelectedFor, got {event:?}");
+    };
+    assert_eq!((target, by), (user, delegate));
+    let nfts = user_client.query_user_nfts_full(user).recv(main_contract_id).await.unwrap();
+    assert_eq!(nfts, selected_nfts);
+    // The delegate's own selection is untouched
+    let nfts = user_client.query_user_nfts(delegate).recv(main_contract_id).await.unwrap();
+    assert_eq!(nfts, None);
+    // Delegation works one way only
+    let res = user_client
+        .nfts_selected_for(delegate, selected_nfts.clone())
+        .send_recv(main_contract_id)
+        .await;
+    assert_panics_with(res, "Unauthorized");
+
+    user_client.revoke_delegate(delegate).send_recv(main_contract_id).await.unwrap();
+    let res = delegate_client
+        .nfts_selected_for(user, selected_nfts)
+        .send_recv(main_contract_id)
+        .await;
+    assert_panics_with(res, "Unauthorized");
+}
+
+#[tokio::test]
+async fn test_max_participants() {
+    let (program_space, main_contract_id) = deploy().await;
+    let mut client = MainClient::new(program_space.clone());
+    for (i, user) in USER_ID.into_iter().enumerate() {
+        select(&program_space, main_contract_id, user, ActorId::from(100), &[i as u64]).await;
+    }
+
+    let res = client_as(&program_space, USER_ID[0])
+        .set_max_participants(2)
+        .send_recv(main_contract_id)
+        .await;
+    assert_panics_with(res, "Only admins");
+    client.set_max_participants(2).send_recv(main_contract_id).await.unwrap();
+
+    let res = client.start_run().send_recv(main_contract_id).await;
+    assert_panics_with(res, "TooManyParticipants");
+    let total = client.query_total_runs_started().recv(main_contract_id).await.unwrap();
+    assert_eq!(total, 0);
+    // Subsets within the cap can still play
+    start_run_with(&program_space, main_contract_id, &USER_ID[..2]).await;
+
+    // Zero removes the cap
+    client.set_max_participants(0).send_recv(main_contract_id).await.unwrap();
+    let event = client.start_run().send_recv(main_contract_id).await.unwrap();
+    assert!(matches!(event, MainEvent::RunStarted { participants, .. } if participants.len() == 3));
+}
+
+#[tokio::test]
+async fn test_user_rank() {
+    let (program_space, main_contract_id) = deploy().await;
+    let client = MainClient::new(program_space.clone());
+    for user in USER_ID {
+        select(&program_space, main_contract_id, user, ActorId::from(100), &[1]).await;
+    }
+    let run_id = start_run_with(&program_space, main_contract_id, &USER_ID).await;
+    for (user, monsters_defeated) in USER_ID.into_iter().zip([5, 9, 5]) {
+        finish_run(&program_space, main_contract_id, user, run_id, monsters_defeated)
+            .await
+            .unwrap();
+    }
+
+    let rank = |user: u64| client.query_user_rank(user.into()).recv(main_contract_id);
+    assert_eq!(rank(USER_ID[1]).await.unwrap(), Some(1));
+    // Ties go to the lower account id
+    assert_eq!(rank(USER_ID[0]).await.unwrap(), Some(2));
+    assert_eq!(rank(USER_ID[2]).await.unwrap(), Some(3));
+    assert_eq!(rank(ADMIN_ID).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn test_weapon_ownership() {
+    let (program_space, main_contract_id) = deploy().await;
+    let (vnft, vnft_id) = deploy_vnft(&program_space);
+    let mut client = MainClient::new(program_space.clone());
+    let mut user_client = client_as(&program_space, USER_ID[0]);
+    let user = ActorId::from(USER_ID[0]);
+
+    let res = user_client
+        .set_weapon_contract(Some(vnft_id))
+        .send_recv(main_contract_id)
+        .await;
+    assert_panics_with(res, "Only admins can perform this action");
+
+    client
+        .set_weapon_contract(Some(vnft_id))
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    let owned = vnft.mint(user, token_metadata("sword"));
+    let unowned = vnft.mint(ActorId::from(USER_ID[1]), token_metadata("axe"));
+
+    let reply = user_client
+        .set_new_selected_weapon(owned)
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    assert!(matches!(reply, MainEvent::NewWeaponSelected { token_id, .. } if token_id == owned));
+    let res = user_client
+        .set_new_selected_weapon(unowned)
+        .send_recv(main_contract_id)
+        .await;
+    assert_panics_with(res, &format!("{:?}", MainError::NotWeaponOwner));
+
+    // Without a weapon contract any token can be selected
+    client
+        .set_weapon_contract(None)
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    let reply = user_client
+        .set_new_selected_weapon(unowned)
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    assert!(matches!(reply, MainEvent::NewWeaponSelected { token_id, .. } if token_id == unowned));
+}
+
+#[tokio::test]
+async fn test_runs_in_range() {
+    let (program_space, main_contract_id) = deploy().await;
+    let client = MainClient::new(program_space.clone());
+    for user in USER_ID {
+        select(&program_space, main_contract_id, user, ActorId::from(100), &[1]).await;
+    }
+    let run_id = start_run_with(&program_space, main_contract_id, &USER_ID).await;
+    // Each submission lands in its own block
+    for user in USER_ID {
+        finish_run(&program_space, main_contract_id, user, run_id, 5).await.unwrap();
+    }
+
+    let runs = client.query_runs_in_range(0, u32::MAX, 0).recv(main_contract_id).await.unwrap();
+    let users: Vec<ActorId> = runs.iter().map(|stats| stats.user).collect();
+    assert_eq!(users, USER_ID.map(ActorId::from));
+    let heights: Vec<u32> = runs.iter().map(|stats| stats.block_height).collect();
+    assert!(heights.windows(2).all(|pair| pair[0] < pair[1]), "{heights:?}");
+
+    // Backups still carry the whole history
+    let snapshot = client.query_full_snapshot().recv(main_contract_id).await.unwrap();
+    assert_eq!(snapshot.run_history, runs);
+    assert_eq!(snapshot.map_key_counts.run_history, 3);
+
+    let runs = client
+        .query_runs_in_range(heights[1], heights[2], 0)
+        .recv(main_contract_id)
+        .await
+        .unwrap();
+    assert_eq!(runs.len(), 2);
+    assert_eq!(runs[0].user, ActorId::from(USER_ID[1]));
+    let runs = client
+        .query_runs_in_range(heights[0], heights[0], 0)
+        .recv(main_contract_id)
+        .await
+        .unwrap();
+    assert_eq!(runs.len(), 1);
+    assert_eq!(runs[0].user, ActorId::from(USER_ID[0]));
+
+    // Paging skips the runs already read
+    let runs = client.query_runs_in_range(0, u32::MAX, 2).recv(main_contract_id).await.unwrap();
+    assert_eq!(runs.len(), 1);
+    assert_eq!(runs[0].user, ActorId::from(USER_ID[2]));
+
+    let res = client.query_runs_in_range(heights[2], heights[0], 0).recv(main_contract_id).await;
+    assert_panics_with(res, &format!("{:?}", MainError::InvalidBlockRange));
+}
+
+#[tokio::test]
+async fn test_key_length() {
+    let (program_space, main_contract_id) = deploy().await;
+    let mut client = MainClient::new(program_space.clone());
+    let mut user_client = client_as(&program_space, USER_ID[0]);
+    let invalid_key = format!("{:?}", MainError::InvalidKey);
+
+    let res = user_client.add_key(vec![], None).send_recv(main_contract_id).await;
+    assert_panics_with(res, &invalid_key);
+    let res = user_client.add_key(vec![1; 66], None).send_recv(main_contract_id).await;
+    assert_panics_with(res, &invalid_key);
+    user_client
+        .add_key(vec![1; 65], None)
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+
+    let res = user_client.set_max_key_len(33).send_recv(main_contract_id).await;
+    assert_panics_with(res, "Only admins can perform this action");
+    client.set_max_key_len(33).send_recv(main_contract_id).await.unwrap();
+
+    // The lowered limit applies to updates as well
+    let res = user_client.update_key(vec![2; 65], None).send_recv(main_contract_id).await;
+    assert_panics_with(res, &invalid_key);
+    let res = user_client.update_key(vec![], None).send_recv(main_contract_id).await;
+    assert_panics_with(res, &invalid_key);
+    user_client
+        .update_key(vec![2; 33], None)
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    let entry = client
+        .query_key(USER_ID[0].into())
+        .recv(main_contract_id)
+        .await
+        .unwrap();
+    assert_eq!(entry.map(|entry| entry.public_key), Some(vec![2; 33]));
+}
+
+#[tokio::test]
+async fn test_failed_award_retry() {
+    let (program_space, main_contract_id) = deploy().await;
+    let (vnft, vnft_id) = deploy_vnft(&program_space);
+    vnft.0.borrow_mut().allowed_schemes = vec!["ipfs://".to_string()];
+    let mut listener = service::events::listener(program_space.clone());
+    let mut events = listener.listen().await.unwrap();
+    let mut client = MainClient::new(program_space.clone());
+    let mut user_client = client_as(&program_space, USER_ID[0]);
+    let user = ActorId::from(USER_ID[0]);
+    client
+        .set_reward_contract(Some(vnft_id))
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    select(&program_space, main_contract_id, USER_ID[0], ActorId::from(100), &[1]).await;
+
+    // Empty media is rejected by the vNFT, the run is still recorded
+    let first_run = start_run_with(&program_space, main_contract_id, &[USER_ID[0]]).await;
+    let event = user_client
+        .finish_run(first_run, 1, vec![], vec![3.into()], 1_000)
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    assert!(matches!(
+        event,
+        MainEvent::RunFinished { awarded_token_ids, .. } if awarded_token_ids.is_empty()
+    ));
+    let failed = loop {
+        let (_, event) = events.next().await.unwrap();
+        if let MainEvent::RewardAwardFailed { user, run_id, error, .. } = event {
+            break (user, run_id, error);
+        }
+    };
+    assert_eq!(failed, (user, first_run, MainError::VnftCallFailed));
+
+    // A later run doesn't hide the first one from a retry
+    client
+        .set_reward_media_base("ipfs://items/".to_string())
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    let second_run = start_run_with(&program_space, main_contract_id, &[USER_ID[0]]).await;
+    let event = user_client
+        .finish_run(second_run, 1, vec![], vec![4.into()], 1_000)
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    let MainEvent::RunFinished { awarded_token_ids: second_tokens, .. } = event else {
+        panic!("expected RunFinished, got {event:?}");
+    };
+    assert_eq!(second_tokens.len(), 1);
+
+    let first_tokens = client
+        .admin_award_items(vnft_id, first_run, user)
+        .send_recv(main_contract_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(first_tokens.len(), 1);
+    let awarded = |run_id: u64| client.query_awarded_rewards(run_id, user).recv(main_contract_id);
+    assert_eq!(awarded(first_run).await.unwrap(), first_tokens);
+    assert_eq!(awarded(second_run).await.unwrap(), second_tokens);
+    let state = vnft.0.borrow();
+    assert_eq!(state.tokens[&first_tokens[0]].1.media, "ipfs://items/3");
+    assert_eq!(state.tokens[&second_tokens[0]].1.media, "ipfs://items/4");
+}
+
+/// Deterministic sr25519 keypair, as a Substrate wallet would derive it from `seed`
+fn keypair(seed: u8) -> Keypair {
+    MiniSecretKey::from_bytes(&[seed; 32])
+        .unwrap()
+        .expand_to_keypair(ExpansionMode::Ed25519)
+}
+
+#[tokio::test]
+async fn test_update_key_signed() {
+    let (program_space, main_contract_id) = deploy().await;
+    let client = MainClient::new(program_space.clone());
+    let mut user_client = client_as(&program_space, USER_ID[0]);
+    let (old_key, new_key) = (keypair(1), keypair(2));
+    let old_public_key = old_key.public.to_bytes().to_vec();
+    let new_public_key = new_key.public.to_bytes().to_vec();
+    let signature = old_key.sign_simple(b"substrate", &new_public_key).to_bytes().to_vec();
+
+    let res = user_client
+        .update_key_signed(new_public_key.clone(), signature.clone(), None)
+        .send_recv(main_contract_id)
+        .await;
+    assert_panics_with(res, &format!("{:?}", MainError::NoKey));
+
+    user_client
+        .add_key(old_public_key.clone(), None)
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    let stored_key = || async {
+        let entry = client.query_key(USER_ID[0].into()).recv(main_contract_id).await.unwrap();
+        entry.unwrap().public_key
+    };
+
+    // Signed by the new key itself, so it proves nothing about the old one
+    let forged = new_key.sign_simple(b"substrate", &new_public_key).to_bytes().to_vec();
+    let res = user_client
+        .update_key_signed(new_public_key.clone(), forged, None)
+        .send_recv(main_contract_id)
+        .await;
+    assert_panics_with(res, &format!("{:?}", MainError::InvalidSignature));
+    assert_eq!(stored_key().await, old_public_key);
+
+    user_client
+        .update_key_signed(new_public_key.clone(), signature, Some("rotated".to_string()))
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    assert_eq!(stored_key().await, new_public_key);
+}
+
+#[tokio::test]
+async fn test_key_origins() {
+    let (program_space, main_contract_id) = deploy().await;
+    let mut client = MainClient::new(program_space.clone());
+    let mut user_client = client_as(&program_space, USER_ID[0]);
+    let user = ActorId::from(USER_ID[0]);
+    let (old_key, new_key) = (keypair(1), keypair(2));
+    let new_public_key = new_key.public.to_bytes().to_vec();
+    let signature = old_key.sign_simple(b"substrate", &new_public_key).to_bytes().to_vec();
+    let origin = |event: MainEvent| match event {
+        MainEvent::KeyUpdated { origin, .. } | MainEvent::KeyRemoved { origin, .. } => origin,
+        other => panic!("expected a key event, got {other:?}"),
+    };
+
+    user_client
+        .add_key(vec![1; 32], None)
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    let updated = user_client
+        .update_key(old_key.public.to_bytes().to_vec(), None)
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    let relabeled = user_client
+        .update_key_metadata(Some("label".to_string()))
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    let rotated = user_client
+        .update_key_signed(new_public_key, signature, None)
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    assert_eq!(
+        [origin(updated), origin(relabeled), origin(rotated)],
+        [KeyOrigin::UpdateKey, KeyOrigin::UpdateKeyMetadata, KeyOrigin::UpdateKeySigned]
+    );
+
+    let removed = user_client.remove_key().send_recv(main_contract_id).await.unwrap();
+    user_client
+        .add_key(vec![1; 32], None)
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    let wiped = client.admin_remove_key(user).send_recv(main_contract_id).await.unwrap();
+    assert_eq!(
+        [origin(removed), origin(wiped)],
+        [KeyOrigin::RemoveKey, KeyOrigin::AdminRemoveKey]
+    );
+}
+
+#[tokio::test]
+async fn test_admin_remove_key() {
+    let (program_space, main_contract_id) = deploy().await;
+    let mut client = MainClient::new(program_space.clone());
+    let user = ActorId::from(USER_ID[0]);
+    client_as(&program_space, USER_ID[0])
+        .add_key(vec![1; 32], None)
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+
+    let res = client_as(&program_space, USER_ID[1])
+        .admin_remove_key(user)
+        .send_recv(main_contract_id)
+        .await;
+    assert_panics_with(res, "Only admins can perform this action");
+    assert!(client.has_key(user).recv(main_contract_id).await.unwrap());
+
+    client.admin_remove_key(user).send_recv(main_contract_id).await.unwrap();
+    assert!(!client.has_key(user).recv(main_contract_id).await.unwrap());
+}
+
+#[tokio::test]
+async fn test_update_key_metadata() {
+    let (program_space, main_contract_id) = deploy().await;
+    let client = MainClient::new(program_space.clone());
+    let mut user_client = client_as(&program_space, USER_ID[0]);
+
+    let res = user_client
+        .update_key_metadata(Some("label".to_string()))
+        .send_recv(main_contract_id)
+        .await;
+    assert_panics_with(res, "No key exists for user");
+
+    user_client
+        .add_key(vec![1; 32], Some("old".to_string()))
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    user_client
+        .update_key_metadata(Some("new".to_string()))
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    let entry = client
+        .query_key(USER_ID[0].into())
+        .recv(main_contract_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(entry.public_key, vec![1; 32]);
+    assert_eq!(entry.metadata.as_deref(), Some("new"));
+}
+
+#[tokio::test]
+async fn test_user_selections_count() {
+    let (program_space, main_contract_id) = deploy().await;
+    let client = MainClient::new(program_space.clone());
+    let count = || client.query_user_selections_count().recv(main_contract_id);
+    assert_eq!(count().await.unwrap(), 0);
+
+    for (selected, user) in USER_ID.into_iter().enumerate() {
+        select(&program_space, main_contract_id, user, ActorId::from(100), &[1, 2]).await;
+        assert_eq!(count().await.unwrap(), selected as u32 + 1);
+    }
+    // Selecting again replaces the selection, the user still counts once
+    select(&program_space, main_contract_id, USER_ID[0], ActorId::from(100), &[3]).await;
+    assert_eq!(count().await.unwrap(), 3);
+
+    client_as(&program_space, USER_ID[1])
+        .cancel_selection()
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    assert_eq!(count().await.unwrap(), 2);
+}
+
+#[tokio::test]
+async fn test_user_nfts_full() {
+    let (program_space, main_contract_id) = deploy().await;
+    let (_, first_vnft) = deploy_vnft(&program_space);
+    let (_, second_vnft) = deploy_vnft(&program_space);
+    let client = MainClient::new(program_space.clone());
+    let user = ActorId::from(USER_ID[0]);
+
+    // Token 1 exists on both contracts
+    let selected_nfts = vec![
+        (first_vnft, U256::from(1)),
+        (second_vnft, U256::from(1)),
+        (second_vnft, U256::from(2)),
+    ];
+    client_as(&program_space, USER_ID[0])
+        .nfts_selected_by_user(selected_nfts.clone())
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+
+    let full = client.query_user_nfts_full(user).recv(main_contract_id).await.unwrap();
+    assert_eq!(full, selected_nfts);
+    let token_ids = client.query_user_nfts(user).recv(main_contract_id).await.unwrap();
+    assert_eq!(token_ids, Some(vec![1.into(), 1.into(), 2.into()]));
+
+    let full = client
+        .query_user_nfts_full(USER_ID[1].into())
+        .recv(main_contract_id)
+        .await
+        .unwrap();
+    assert!(full.is_empty());
+}
+
+#[tokio::test]
+async fn test_contract_selection_counts() {
+    let (program_space, main_contract_id) = deploy().await;
+    let client = MainClient::new(program_space.clone());
+    let (first, second) = (ActorId::from(100), ActorId::from(200));
+    let counts = || client.query_contract_selection_counts().recv(main_contract_id);
+    assert!(counts().await.unwrap().is_empty());
+
+    select(&program_space, main_contract_id, USER_ID[0], first, &[1, 2]).await;
+    select(&program_space, main_contract_id, USER_ID[1], second, &[1]).await;
+    client_as(&program_space, USER_ID[2])
+        .nfts_selected_by_user(vec![(first, 3.into()), (second, 2.into())])
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    // Most selected first
+    assert_eq!(counts().await.unwrap(), vec![(first, 3), (second, 2)]);
+
+    client_as(&program_space, USER_ID[0])
+        .cancel_selection()
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+    assert_eq!(counts().await.unwrap(), vec![(second, 2), (first, 1)]);
+}
+
+#[tokio::test]
+async fn test_active_since() {
+    let (program_space, main_contract_id) = deploy().await;
+    let client = MainClient::new(program_space.clone());
+    // Each selection lands in its own block
+    for user in USER_ID {
+        select(&program_space, main_contract_id, user, ActorId::from(100), &[1]).await;
+    }
+    let state = client.query_state().recv(main_contract_id).await.unwrap();
+    let active_at = |user: u64| {
+        let user = ActorId::from(user);
+        state.last_active_block.iter().find(|(active, _)| *active == user).unwrap().1
+    };
+    let cutoff = active_at(USER_ID[1]);
+    assert!(active_at(USER_ID[0]) < cutoff && cutoff < active_at(USER_ID[2]));
+
+    let mut active = client.query_active_since(cutoff).recv(main_contract_id).await.unwrap();
+    active.sort();
+    assert_eq!(active, vec![ActorId::from(USER_ID[1]), ActorId::from(USER_ID[2])]);
+    let active = client
+        .query_active_since(active_at(USER_ID[2]) + 1)
+        .recv(main_contract_id)
+        .await
+        .unwrap();
+    assert!(active.is_empty());
+}
+
+#[tokio::test]
+async fn test_user_selection_by_contract() {
+    let (program_space, main_contract_id) = deploy().await;
+    let client = MainClient::new(program_space.clone());
+    let (first, second) = (ActorId::from(100), ActorId::from(200));
+    client_as(&program_space, USER_ID[0])
+        .nfts_selected_by_user(vec![
+            (second, 5.into()),
+            (first, 1.into()),
+            (second, 6.into()),
+            (first, 2.into()),
+        ])
+        .send_recv(main_contract_id)
+        .await
+        .unwrap();
+
+    // Grouped in first-seen order, token order kept within a contract
+    let grouped = client
+        .query_user_selection_by_contract(USER_ID[0].into())
+        .recv(main_contract_id)
+        .await
+        .unwrap();
+    assert_eq!(
+        grouped,
+        vec![(second, vec![5.into(), 6.into()]), (first, vec![1.into(), 2.into()])]
+    );
+    let grouped = client
+        .query_user_selection_by_contract(USER_ID[1].into())
+        .recv(main_contract_id)
+        .await
+        .unwrap();
+    assert!(grouped.is_empty());
+}
+
+#[tokio::test]
+async fn test_distinct_selected_contracts() {
+    let (program_space, main_contract_id) = deploy().await;
+    let client = MainClient::new(program_space.clone());
+    let distinct = || client.query_distinct_selected_contracts().recv(main_contract_id);
+    assert_eq!(distinct().await.unwrap(), 0);
+
+    select(&program_space, main_contract_id, USER_ID[0], ActorId::from(100), &[1, 2]).await;
+    select(&program_space, main_contract_id, USER_ID[1], ActorId::from(100), &[3]).await;
+    assert_eq!(distinct().await.unwrap(), 1);
+    select(&program_space, main_contract_id, USER_ID[2], ActorId::from(200), &[1]).await;
+    assert_eq!(distinct().await.unwrap(), 2);
+
+    for user in USER_ID {
+        client_as(&program_space, user)
+            .cancel_selection()
+            .send_recv(main_contract_id)
+            .await
+            .unwrap();
+    }
+    assert_eq!(distinct().await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn test_version() {
+    let (program_space, main_contract_id) = deploy().await;
+    let client = MainClient::new(program_space);
+
+    let version = client.version().recv(main_contract_id).await.unwrap();
+    assert_eq!(version, 29);
+}
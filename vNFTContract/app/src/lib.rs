@@ -13,6 +13,11 @@ impl ExtendedVnftProgram {
         Self(())
     }
 
+    pub fn new_with_admins(name: String, symbol: String, admins: Vec<ActorId>) -> Self {
+        ExtendedService::init_with_admins(name, symbol, admins);
+        Self(())
+    }
+
     pub fn vnft(&self) -> ExtendedService {
         ExtendedService::new()
     }
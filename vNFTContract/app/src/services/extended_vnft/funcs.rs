@@ -1,10 +1,12 @@
-use crate::services::extended_vnft::TokenMetadata;
+use crate::services::extended_vnft::{ExtendedError, TokenMetadata};
 use sails_rs::{
     collections::{HashMap, HashSet},
     prelude::*,
 };
 use vnft_service::utils::{Error, Result, *};
 
+type ExtendedResult<T> = core::result::Result<T, ExtendedError>;
+
 pub fn mint(
     owner_by_id: &mut HashMap<TokenId, ActorId>,
     tokens_for_owner: &mut HashMap<ActorId, HashSet<TokenId>>,
@@ -13,16 +15,64 @@ pub fn mint(
     to: ActorId,
     token_metadata: TokenMetadata,
 ) -> Result<()> {
-    owner_by_id.insert(*token_id, to);
+    insert_token(
+        owner_by_id,
+        tokens_for_owner,
+        token_metadata_by_id,
+        *token_id,
+        to,
+        token_metadata,
+    );
+    *token_id += 1.into();
+    Ok(())
+}
+
+pub fn mint_with_id(
+    owner_by_id: &mut HashMap<TokenId, ActorId>,
+    tokens_for_owner: &mut HashMap<ActorId, HashSet<TokenId>>,
+    token_metadata_by_id: &mut HashMap<TokenId, TokenMetadata>,
+    next_token_id: &mut TokenId,
+    token_id: TokenId,
+    to: ActorId,
+    token_metadata: TokenMetadata,
+) -> ExtendedResult<()> {
+    if owner_by_id.contains_key(&token_id) {
+        return Err(ExtendedError::AlreadyExists);
+    }
+    let next = token_id
+        .checked_add(1.into())
+        .ok_or(ExtendedError::Overflow)?;
+    insert_token(
+        owner_by_id,
+        tokens_for_owner,
+        token_metadata_by_id,
+        token_id,
+        to,
+        token_metadata,
+    );
+    // keep auto-minted ids clear of the migrated ones
+    if next > *next_token_id {
+        *next_token_id = next;
+    }
+    Ok(())
+}
+
+fn insert_token(
+    owner_by_id: &mut HashMap<TokenId, ActorId>,
+    tokens_for_owner: &mut HashMap<ActorId, HashSet<TokenId>>,
+    token_metadata_by_id: &mut HashMap<TokenId, TokenMetadata>,
+    token_id: TokenId,
+    to: ActorId,
+    token_metadata: TokenMetadata,
+) {
+    owner_by_id.insert(token_id, to);
     tokens_for_owner
         .entry(to)
         .and_modify(|tokens| {
-            tokens.insert(*token_id);
+            tokens.insert(token_id);
         })
-        .or_insert_with(|| HashSet::from([*token_id]));
-    token_metadata_by_id.insert(*token_id, token_metadata);
-    *token_id += 1.into();
-    Ok(())
+        .or_insert_with(|| HashSet::from([token_id]));
+    token_metadata_by_id.insert(token_id, token_metadata);
 }
 
 pub fn burn(
@@ -30,6 +80,7 @@ pub fn burn(
     tokens_for_owner: &mut HashMap<ActorId, HashSet<TokenId>>,
     token_approvals: &mut HashMap<TokenId, ActorId>,
     token_metadata_by_id: &mut HashMap<TokenId, TokenMetadata>,
+    burned_tokens: &mut HashMap<TokenId, TokenMetadata>,
     token_id: TokenId,
 ) -> Result<()> {
     let owner = owner_by_id
@@ -42,6 +93,84 @@ pub fn burn(
         }
     }
     token_approvals.remove(&token_id);
-    token_metadata_by_id.remove(&token_id);
+    // keep the last known metadata for provenance
+    if let Some(token_metadata) = token_metadata_by_id.remove(&token_id) {
+        burned_tokens.insert(token_id, token_metadata);
+    }
     Ok(())
 }
+
+/// Checks a whole burn batch before anything is burned, so the batch is all-or-nothing
+pub fn check_burn_batch(
+    owner_by_id: &HashMap<TokenId, ActorId>,
+    owner: ActorId,
+    token_ids: &[TokenId],
+) -> ExtendedResult<()> {
+    let unique: HashSet<&TokenId> = token_ids.iter().collect();
+    if unique.len() != token_ids.len() {
+        return Err(ExtendedError::DuplicateTokenId);
+    }
+    if token_ids
+        .iter()
+        .any(|token_id| owner_by_id.get(token_id) != Some(&owner))
+    {
+        return Err(ExtendedError::NotOwner);
+    }
+    Ok(())
+}
+
+pub fn set_description(
+    owner_by_id: &HashMap<TokenId, ActorId>,
+    token_metadata_by_id: &mut HashMap<TokenId, TokenMetadata>,
+    owner: ActorId,
+    token_id: TokenId,
+    description: String,
+) -> ExtendedResult<()> {
+    if owner_by_id.get(&token_id) != Some(&owner) {
+        return Err(ExtendedError::NotOwner);
+    }
+    let metadata = token_metadata_by_id
+        .get_mut(&token_id)
+        .ok_or(ExtendedError::NotOwner)?;
+    metadata.description = description;
+    Ok(())
+}
+
+pub fn force_transfer(
+    owner_by_id: &mut HashMap<TokenId, ActorId>,
+    tokens_for_owner: &mut HashMap<ActorId, HashSet<TokenId>>,
+    token_approvals: &mut HashMap<TokenId, ActorId>,
+    to: ActorId,
+    token_id: TokenId,
+) -> Result<ActorId> {
+    let from = *owner_by_id
+        .get(&token_id)
+        .ok_or(Error::TokenDoesNotExist)?;
+    owner_by_id.insert(token_id, to);
+    if let Some(tokens) = tokens_for_owner.get_mut(&from) {
+        tokens.remove(&token_id);
+        if tokens.is_empty() {
+            tokens_for_owner.remove(&from);
+        }
+    }
+    tokens_for_owner
+        .entry(to)
+        .and_modify(|tokens| {
+            tokens.insert(token_id);
+        })
+        .or_insert_with(|| HashSet::from([token_id]));
+    token_approvals.remove(&token_id);
+    Ok(from)
+}
+
+/// Recomputes the per-owner index from the token owners, returns the number of tokens indexed
+pub fn rebuild_owner_index(
+    owner_by_id: &HashMap<TokenId, ActorId>,
+    tokens_for_owner: &mut HashMap<ActorId, HashSet<TokenId>>,
+) -> u32 {
+    tokens_for_owner.clear();
+    for (token_id, owner) in owner_by_id {
+        tokens_for_owner.entry(*owner).or_default().insert(*token_id);
+    }
+    owner_by_id.len() as u32
+}
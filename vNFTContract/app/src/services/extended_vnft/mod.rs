@@ -1,5 +1,5 @@
 #![allow(static_mut_refs)]
-use gstd::msg;
+use gstd::{exec, msg};
 use sails_rs::{
     collections::{HashMap, HashSet},
     gstd::service,
@@ -17,6 +17,35 @@ pub struct ExtendedStorage {
     burners: HashSet<ActorId>,
     admins: HashSet<ActorId>,
     token_metadata_by_id: HashMap<TokenId, TokenMetadata>,
+    main_contract: Option<ActorId>,
+    token_blocks: HashMap<TokenId, TokenBlocks>,
+    /// Media URL prefixes accepted at mint, empty means anything goes
+    allowed_schemes: Vec<String>,
+    burned_tokens: HashMap<TokenId, TokenMetadata>,
+    transfer_counts: HashMap<TokenId, u32>,
+    event_seq: u64,
+    /// Tokens that can never leave their owner, only be burned
+    soulbound: HashSet<TokenId>,
+    /// Owners of each token in order, starting with the minted-to account
+    ownership_history: HashMap<TokenId, Vec<ActorId>>,
+    /// Mints allowed per `MINT_WINDOW_BLOCKS` before minting auto-pauses, `None` disables the breaker
+    mint_burst_threshold: Option<u32>,
+    mint_window_start: u32,
+    mints_in_window: u32,
+    minting_paused: bool,
+    /// Tokens the main contract has frozen, e.g. while equipped in a run
+    locked_tokens: HashSet<TokenId>,
+    /// Next token id at which `IdSpaceWarning` fires, `None` disables it
+    id_warning_threshold: Option<TokenId>,
+    id_warning_emitted: bool,
+    /// Type tags of tokens minted through `mint_unique`
+    type_tags: HashMap<TokenId, String>,
+    /// Ids each owner burned, most recent last
+    retired: HashMap<ActorId, Vec<TokenId>>,
+    /// Events are skipped while `false`, state changes still happen
+    emit_events: bool,
+    /// Tokens whose metadata can never change again
+    frozen_metadata: HashSet<TokenId>,
 }
 
 #[derive(Default, Debug, Encode, Decode, TypeInfo, Clone)]
@@ -29,8 +58,101 @@ pub struct TokenMetadata {
     pub reference: String, // URL to an off-chain JSON file with more info
 }
 
+/// Block heights at which a token was minted and its metadata last changed
+#[derive(Default, Debug, Encode, Decode, TypeInfo, Clone, Copy)]
+#[codec(crate = sails_rs::scale_codec)]
+#[scale_info(crate = sails_rs::scale_info)]
+pub struct TokenBlocks {
+    pub created_at: u32,
+    pub last_updated_at: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode, TypeInfo)]
+#[codec(crate = sails_rs::scale_codec)]
+#[scale_info(crate = sails_rs::scale_info)]
+pub enum TokenStatus {
+    NeverExisted,
+    Live,
+    Burned,
+}
+
+/// Live tokens sorted by id, `truncated` means more exist than fit in one reply
+/// and the rest should be fetched with `available_nfts_page`
+#[derive(Default, Debug, Encode, Decode, TypeInfo, Clone)]
+#[codec(crate = sails_rs::scale_codec)]
+#[scale_info(crate = sails_rs::scale_info)]
+pub struct AvailableNfts {
+    pub nfts: Vec<(TokenId, TokenMetadata)>,
+    pub truncated: bool,
+}
+
+/// Snapshot of the contract settings, maps are reported by their number of entries
+/// Role lists are sorted so equal states compare equal
+#[derive(Default, Debug, Encode, Decode, TypeInfo, Clone)]
+#[codec(crate = sails_rs::scale_codec)]
+#[scale_info(crate = sails_rs::scale_info)]
+pub struct IoVnftState {
+    pub next_token_id: TokenId,
+    pub admins: Vec<ActorId>,
+    pub minters: Vec<ActorId>,
+    pub burners: Vec<ActorId>,
+    pub main_contract: Option<ActorId>,
+    pub allowed_schemes: Vec<String>,
+    pub minting_paused: bool,
+    pub mint_burst_threshold: Option<u32>,
+    pub emit_events: bool,
+    pub event_seq: u64,
+    pub live_tokens: u32,
+    pub burned_tokens: u32,
+    pub locked_tokens: u32,
+}
+
+#[derive(Debug, Clone, Encode, Decode, TypeInfo)]
+#[codec(crate = sails_rs::scale_codec)]
+#[scale_info(crate = sails_rs::scale_info)]
+pub enum ExtendedError {
+    AlreadyExists,
+    Overflow,
+    Unauthorized,
+    NotOwner,
+    BatchTooLarge,
+    InvalidMetadata,
+    ZeroAddress,
+    Soulbound,
+    MintingPaused,
+    Locked,
+    MetadataFrozen,
+    DuplicateTokenId,
+}
+
+/// Contract version for client compatibility checks, bump it whenever the state schema changes
+pub const VERSION: u32 = 11;
+
+/// Max number of tokens handled by a single batch call
+const MAX_BATCH_SIZE: usize = 50;
+
+/// Max number of tokens returned by a single listing query
+const MAX_QUERY_ITEMS: usize = 100;
+
+/// Length of the window the mint circuit breaker counts over
+const MINT_WINDOW_BLOCKS: u32 = 100;
+
+/// Max lengths in bytes of token metadata fields, bounding what a minter can store
+const MAX_NAME_LEN: usize = 128;
+const MAX_DESCRIPTION_LEN: usize = 1024;
+const MAX_URL_LEN: usize = 512;
+
+/// Max number of owners kept per token, the oldest ones are dropped first
+const MAX_OWNERSHIP_HISTORY: usize = 32;
+
+/// Max number of retired ids kept per owner, the oldest ones are dropped first
+const MAX_RETIRED_PER_OWNER: usize = 100;
+
 static mut EXTENDED_STORAGE: Option<ExtendedStorage> = None;
 
+/// Every event carries the contract's event sequence number as its last field
+/// Events of the base vNFT service, e.g. `Approval` and `Transfer`, are not numbered,
+/// indexers have to order those by block and position within it
 #[derive(Encode, Decode, TypeInfo)]
 #[codec(crate = sails_rs::scale_codec)]
 #[scale_info(crate = sails_rs::scale_info)]
@@ -38,11 +160,67 @@ pub enum Event {
     Minted {
         to: ActorId,
         token_metadata: TokenMetadata,
+        token_id: TokenId,
+        seq: u64,
     },
     Burned {
         from: ActorId,
         token_id: TokenId,
+        seq: u64,
+    },
+    Transferred {
+        from: ActorId,
+        to: ActorId,
+        token_id: TokenId,
+        initiator: ActorId,
+        seq: u64,
+    },
+    MainContractSet(ActorId, u64),
+    MainContractCleared(u64),
+    DescriptionUpdated {
+        token_id: TokenId,
+        seq: u64,
+    },
+    AutoPaused {
+        mints_in_window: u32,
+        seq: u64,
+    },
+    MintingUnpaused(u64),
+    TokenLocked {
+        token_id: TokenId,
+        seq: u64,
+    },
+    TokenUnlocked {
+        token_id: TokenId,
+        seq: u64,
+    },
+    AllLocksReleased {
+        count: u32,
+        seq: u64,
+    },
+    TokensLocked {
+        token_ids: Vec<TokenId>,
+        locked: bool,
+        seq: u64,
+    },
+    IdSpaceWarning {
+        remaining: TokenId,
+        seq: u64,
     },
+    OwnerIndexRebuilt {
+        token_count: u32,
+        seq: u64,
+    },
+    MetadataFrozen {
+        token_id: TokenId,
+        seq: u64,
+    },
+    UnauthorizedAttempt {
+        caller: ActorId,
+        action: String,
+        seq: u64,
+    },
+    EmitEventsSet(bool, u64),
 }
 #[derive(Clone)]
 pub struct ExtendedService {
@@ -51,12 +229,21 @@ pub struct ExtendedService {
 
 impl ExtendedService {
     pub fn init(name: String, symbol: String) -> Self {
-        let admin = msg::source();
+        Self::init_with_admins(name, symbol, vec![msg::source()])
+    }
+
+    /// Seeds the contract with several admins, each also granted the minter and burner roles
+    pub fn init_with_admins(name: String, symbol: String, admins: Vec<ActorId>) -> Self {
+        if admins.is_empty() {
+            panic!("Admin list can't be empty")
+        }
+        let admins: HashSet<ActorId> = admins.into_iter().collect();
         unsafe {
             EXTENDED_STORAGE = Some(ExtendedStorage {
-                admins: [admin].into(),
-                minters: [admin].into(),
-                burners: [admin].into(),
+                minters: admins.clone(),
+                burners: admins.clone(),
+                admins,
+                emit_events: true,
                 ..Default::default()
             });
         };
@@ -92,35 +279,370 @@ impl ExtendedService {
         if !self.get().minters.contains(&msg::source()) {
             panic!("Not allowed to mint")
         };
+        self.ensure_minting_enabled();
+        Self::ensure_not_zero(to);
+        services::utils::panicking(|| self.validate_metadata(&token_metadata));
+        self.mint_token(to, token_metadata);
+        self.record_mint_burst(1);
+    }
+
+    /// Mints a non-transferable token, e.g. an achievement
+    pub fn mint_soulbound(&mut self, to: ActorId, token_metadata: TokenMetadata) {
+        if !self.get().minters.contains(&msg::source()) {
+            panic!("Not allowed to mint")
+        };
+        self.ensure_minting_enabled();
+        Self::ensure_not_zero(to);
+        services::utils::panicking(|| self.validate_metadata(&token_metadata));
+        let token_id = self.mint_token(to, token_metadata);
+        self.get_mut().soulbound.insert(token_id);
+        self.record_mint_burst(1);
+    }
+
+    /// Mints a token tagged `type_tag`, failing if `to` already owns one with the same tag,
+    /// e.g. a single starter weapon per player
+    pub fn mint_unique(&mut self, to: ActorId, token_metadata: TokenMetadata, type_tag: String) {
+        if !self.get().minters.contains(&msg::source()) {
+            panic!("Not allowed to mint")
+        };
+        self.ensure_minting_enabled();
+        Self::ensure_not_zero(to);
+        if type_tag.len() > MAX_NAME_LEN {
+            services::utils::panic(ExtendedError::InvalidMetadata);
+        }
+        services::utils::panicking(|| self.validate_metadata(&token_metadata));
+        let type_tags = &self.get().type_tags;
+        let owns_tag = Storage::tokens_for_owner()
+            .get(&to)
+            .is_some_and(|tokens| {
+                tokens
+                    .iter()
+                    .any(|token_id| type_tags.get(token_id) == Some(&type_tag))
+            });
+        if owns_tag {
+            services::utils::panic(ExtendedError::AlreadyExists);
+        }
+        let token_id = self.mint_token(to, token_metadata);
+        self.get_mut().type_tags.insert(token_id, type_tag);
+        self.record_mint_burst(1);
+    }
+
+    /// Mints several tokens to `to` in one call and returns their ids in order,
+    /// callable by minters and by the main contract
+    pub fn mint_batch(&mut self, to: ActorId, token_metadata: Vec<TokenMetadata>) -> Vec<TokenId> {
+        let caller = msg::source();
+        if !self.get().minters.contains(&caller) && self.get().main_contract != Some(caller) {
+            panic!("Not allowed to mint")
+        };
+        if token_metadata.len() > MAX_BATCH_SIZE {
+            services::utils::panic(ExtendedError::BatchTooLarge);
+        }
+        self.ensure_minting_enabled();
+        Self::ensure_not_zero(to);
+        for metadata in &token_metadata {
+            services::utils::panicking(|| self.validate_metadata(metadata));
+        }
+        let token_ids: Vec<TokenId> = token_metadata
+            .into_iter()
+            .map(|metadata| self.mint_token(to, metadata))
+            .collect();
+        self.record_mint_burst(token_ids.len() as u32);
+        token_ids
+    }
+
+    /// Mints a token under an explicit id, used when migrating from an old contract
+    /// (only admins can do this)
+    pub fn admin_mint_with_id(
+        &mut self,
+        to: ActorId,
+        token_id: TokenId,
+        token_metadata: TokenMetadata,
+    ) {
+        self.ensure_is_admin();
+        Self::ensure_not_zero(to);
+        services::utils::panicking(|| self.validate_metadata(&token_metadata));
         services::utils::panicking(|| {
-            funcs::mint(
+            funcs::mint_with_id(
                 Storage::owner_by_id(),
                 Storage::tokens_for_owner(),
                 &mut self.get_mut().token_metadata_by_id,
                 &mut self.get_mut().token_id,
+                token_id,
                 to,
                 token_metadata.clone(),
             )
         });
-        self.emit_event(Event::Minted { to, token_metadata })
-            .expect("Notification Error");
+        self.record_minted(to, token_id);
+        let seq = self.next_event_seq();
+        self.notify(Event::Minted {
+            to,
+            token_metadata,
+            token_id,
+            seq,
+        });
+        self.check_id_space();
     }
 
     pub fn burn(&mut self, from: ActorId, token_id: TokenId) {
         if !self.get().burners.contains(&msg::source()) {
             panic!("Not allowed to burn")
         };
+        self.ensure_not_locked(token_id);
+        let owner = Storage::owner_by_id().get(&token_id).copied();
         services::utils::panicking(|| {
             funcs::burn(
                 Storage::owner_by_id(),
                 Storage::tokens_for_owner(),
                 Storage::token_approvals(),
                 &mut self.get_mut().token_metadata_by_id,
+                &mut self.get_mut().burned_tokens,
+                token_id,
+            )
+        });
+        if let Some(owner) = owner {
+            self.record_burned(owner, token_id);
+        }
+        let seq = self.next_event_seq();
+        self.notify(Event::Burned {
+            from,
+            token_id,
+            seq,
+        });
+    }
+
+    /// Moves any token on the owner's behalf, only callable by the configured main contract
+    pub fn main_transfer(&mut self, to: ActorId, token_id: TokenId) {
+        self.ensure_is_main_contract();
+        Self::ensure_not_zero(to);
+        self.ensure_transferable(token_id);
+        let from = services::utils::panicking(|| {
+            funcs::force_transfer(
+                Storage::owner_by_id(),
+                Storage::tokens_for_owner(),
+                Storage::token_approvals(),
+                to,
+                token_id,
+            )
+        });
+        self.record_transfer(from, to, token_id, msg::source());
+    }
+
+    pub fn transfer(&mut self, to: ActorId, token_id: TokenId) {
+        Self::ensure_not_zero(to);
+        self.ensure_transferable(token_id);
+        let from = msg::source();
+        self.vnft.transfer(to, token_id);
+        self.record_transfer(from, to, token_id, from);
+    }
+
+    /// Same as the base `transfer_from`, additionally reporting the operator as initiator
+    pub fn transfer_from(&mut self, from: ActorId, to: ActorId, token_id: TokenId) {
+        Self::ensure_not_zero(to);
+        self.ensure_transferable(token_id);
+        self.vnft.transfer_from(from, to, token_id);
+        self.record_transfer(from, to, token_id, msg::source());
+    }
+
+    /// Lets the owner annotate their token, only the description is touched
+    pub fn set_description(&mut self, token_id: TokenId, description: String) {
+        if description.len() > MAX_DESCRIPTION_LEN {
+            services::utils::panic(ExtendedError::InvalidMetadata);
+        }
+        if self.get().frozen_metadata.contains(&token_id) {
+            services::utils::panic(ExtendedError::MetadataFrozen);
+        }
+        services::utils::panicking(|| {
+            funcs::set_description(
+                Storage::owner_by_id(),
+                &mut self.get_mut().token_metadata_by_id,
+                msg::source(),
                 token_id,
+                description,
             )
         });
-        self.emit_event(Event::Burned { from, token_id })
-            .expect("Notification Error");
+        if let Some(blocks) = self.get_mut().token_blocks.get_mut(&token_id) {
+            blocks.last_updated_at = exec::block_height();
+        }
+        let seq = self.next_event_seq();
+        self.notify(Event::DescriptionUpdated { token_id, seq });
+    }
+
+    /// Makes the token's metadata immutable, only the owner can do this and it can't be undone
+    pub fn freeze_metadata(&mut self, token_id: TokenId) {
+        if Storage::owner_by_id().get(&token_id) != Some(&msg::source()) {
+            services::utils::panic(ExtendedError::NotOwner);
+        }
+        if self.get_mut().frozen_metadata.insert(token_id) {
+            let seq = self.next_event_seq();
+            self.notify(Event::MetadataFrozen { token_id, seq });
+        }
+    }
+
+    /// Rejected callers leave an `UnauthorizedAttempt` breadcrumb, so this returns
+    /// an error instead of panicking
+    pub fn set_main_contract(&mut self, main_contract: ActorId) -> Result<(), ExtendedError> {
+        let caller = msg::source();
+        if !self.get().admins.contains(&caller) {
+            let seq = self.next_event_seq();
+            self.notify(Event::UnauthorizedAttempt {
+                caller,
+                action: "set_main_contract".into(),
+                seq,
+            });
+            return Err(ExtendedError::Unauthorized);
+        }
+        self.get_mut().main_contract = Some(main_contract);
+        let seq = self.next_event_seq();
+        self.notify(Event::MainContractSet(main_contract, seq));
+        Ok(())
+    }
+
+    /// Burns several of the caller's own tokens, failing as a whole if any isn't owned
+    /// or is listed twice
+    pub fn burn_batch(&mut self, token_ids: Vec<TokenId>) {
+        if token_ids.len() > MAX_BATCH_SIZE {
+            services::utils::panic(ExtendedError::BatchTooLarge);
+        }
+        for token_id in &token_ids {
+            self.ensure_not_locked(*token_id);
+        }
+        let from = msg::source();
+        services::utils::panicking(|| {
+            funcs::check_burn_batch(Storage::owner_by_id(), from, &token_ids)
+        });
+        for token_id in token_ids {
+            services::utils::panicking(|| {
+                funcs::burn(
+                    Storage::owner_by_id(),
+                    Storage::tokens_for_owner(),
+                    Storage::token_approvals(),
+                    &mut self.get_mut().token_metadata_by_id,
+                    &mut self.get_mut().burned_tokens,
+                    token_id,
+                )
+            });
+            self.record_burned(from, token_id);
+            let seq = self.next_event_seq();
+            self.notify(Event::Burned {
+                from,
+                token_id,
+                seq,
+            });
+        }
+    }
+
+    /// Freezes a token so it can't be transferred or burned, only callable by the main contract
+    pub fn lock_token(&mut self, token_id: TokenId) {
+        self.ensure_is_main_contract();
+        if !Storage::owner_by_id().contains_key(&token_id) {
+            panic!("Token does not exist")
+        }
+        if self.get_mut().locked_tokens.insert(token_id) {
+            let seq = self.next_event_seq();
+            self.notify(Event::TokenLocked { token_id, seq });
+        }
+    }
+
+    pub fn unlock_token(&mut self, token_id: TokenId) {
+        self.ensure_is_main_contract();
+        if self.get_mut().locked_tokens.remove(&token_id) {
+            let seq = self.next_event_seq();
+            self.notify(Event::TokenUnlocked { token_id, seq });
+        }
+    }
+
+    /// Freezes or unfreezes the listed tokens, e.g. ones flagged as disputed
+    /// Ids that don't exist are skipped and left out of the event
+    pub fn admin_set_locked(&mut self, token_ids: Vec<TokenId>, locked: bool) {
+        self.ensure_is_admin();
+        if token_ids.len() > MAX_BATCH_SIZE {
+            services::utils::panic(ExtendedError::BatchTooLarge);
+        }
+        let owner_by_id = Storage::owner_by_id();
+        let token_ids: Vec<TokenId> = token_ids
+            .into_iter()
+            .filter(|token_id| owner_by_id.contains_key(token_id))
+            .collect();
+        let locked_tokens = &mut self.get_mut().locked_tokens;
+        for token_id in &token_ids {
+            if locked {
+                locked_tokens.insert(*token_id);
+            } else {
+                locked_tokens.remove(token_id);
+            }
+        }
+        let seq = self.next_event_seq();
+        self.notify(Event::TokensLocked {
+            token_ids,
+            locked,
+            seq,
+        });
+    }
+
+    /// Releases every lock, e.g. before swapping out a main contract that can no longer unlock
+    pub fn admin_release_all_locks(&mut self) {
+        self.ensure_is_admin();
+        let count = self.get_mut().locked_tokens.drain().count() as u32;
+        let seq = self.next_event_seq();
+        self.notify(Event::AllLocksReleased { count, seq });
+    }
+
+    /// Safety net: rebuilds the per-owner token index from token ownership
+    pub fn admin_rebuild_owner_index(&mut self) {
+        self.ensure_is_admin();
+        let token_count =
+            funcs::rebuild_owner_index(Storage::owner_by_id(), Storage::tokens_for_owner());
+        let seq = self.next_event_seq();
+        self.notify(Event::OwnerIndexRebuilt { token_count, seq });
+    }
+
+    /// Unlinks the main contract, disabling every main-contract-gated call
+    pub fn clear_main_contract(&mut self) {
+        self.ensure_is_admin();
+        self.get_mut().main_contract = None;
+        let seq = self.next_event_seq();
+        self.notify(Event::MainContractCleared(seq));
+    }
+
+    /// Restricts media URLs to the given schemes, e.g. `ipfs://` or `https://`
+    pub fn set_allowed_schemes(&mut self, schemes: Vec<String>) {
+        self.ensure_is_admin();
+        self.get_mut().allowed_schemes = schemes;
+    }
+
+    /// Arms the mint circuit breaker, `None` disables it
+    pub fn set_mint_burst_threshold(&mut self, threshold: Option<u32>) {
+        self.ensure_is_admin();
+        self.get_mut().mint_burst_threshold = threshold;
+    }
+
+    /// Warns once the next token id reaches `threshold`, `None` disables the warning
+    pub fn set_id_warning_threshold(&mut self, threshold: Option<TokenId>) {
+        self.ensure_is_admin();
+        let storage = self.get_mut();
+        storage.id_warning_threshold = threshold;
+        storage.id_warning_emitted = false;
+    }
+
+    /// Turns event emission on or off, e.g. for load testing
+    /// `EmitEventsSet` goes out before muting and after unmuting, accounting for seq gaps
+    pub fn set_emit_events(&mut self, emit_events: bool) {
+        self.ensure_is_admin();
+        self.get_mut().emit_events |= emit_events;
+        let seq = self.next_event_seq();
+        self.notify(Event::EmitEventsSet(emit_events, seq));
+        self.get_mut().emit_events = emit_events;
+    }
+
+    /// Resumes minting after the circuit breaker tripped
+    pub fn unpause_minting(&mut self) {
+        self.ensure_is_admin();
+        let storage = self.get_mut();
+        storage.minting_paused = false;
+        storage.mint_window_start = exec::block_height();
+        storage.mints_in_window = 0;
+        let seq = self.next_event_seq();
+        self.notify(Event::MintingUnpaused(seq));
     }
 
     pub fn grant_admin_role(&mut self, to: ActorId) {
@@ -148,6 +670,9 @@ impl ExtendedService {
         self.ensure_is_admin();
         self.get_mut().burners.remove(&from);
     }
+    pub fn version(&self) -> u32 {
+        VERSION
+    }
     pub fn minters(&self) -> Vec<ActorId> {
         self.get().minters.clone().into_iter().collect()
     }
@@ -159,12 +684,165 @@ impl ExtendedService {
     pub fn admins(&self) -> Vec<ActorId> {
         self.get().admins.clone().into_iter().collect()
     }
+    pub fn allowed_schemes(&self) -> Vec<String> {
+        self.get().allowed_schemes.clone()
+    }
+    pub fn main_contract(&self) -> Option<ActorId> {
+        self.get().main_contract
+    }
     pub fn token_id(&self) -> TokenId {
         self.get().token_id
     }
+    /// Contract state along with the block height it was read at, so clients can tell
+    /// how fresh their view is
+    pub fn query_state_at(&self) -> (IoVnftState, u32) {
+        let storage = self.get();
+        let sorted = |actors: &HashSet<ActorId>| {
+            let mut actors: Vec<ActorId> = actors.iter().copied().collect();
+            actors.sort();
+            actors
+        };
+        let state = IoVnftState {
+            next_token_id: storage.token_id,
+            admins: sorted(&storage.admins),
+            minters: sorted(&storage.minters),
+            burners: sorted(&storage.burners),
+            main_contract: storage.main_contract,
+            allowed_schemes: storage.allowed_schemes.clone(),
+            minting_paused: storage.minting_paused,
+            mint_burst_threshold: storage.mint_burst_threshold,
+            emit_events: storage.emit_events,
+            event_seq: storage.event_seq,
+            live_tokens: storage.token_metadata_by_id.len() as u32,
+            burned_tokens: storage.burned_tokens.len() as u32,
+            locked_tokens: storage.locked_tokens.len() as u32,
+        };
+        (state, exec::block_height())
+    }
     pub fn token_metadata_by_id(&self, token_id: TokenId) -> Option<TokenMetadata> {
         self.get().token_metadata_by_id.get(&token_id).cloned()
     }
+    /// Metadata for each requested id, positionally aligned with the input
+    pub fn tokens_by_ids(&self, token_ids: Vec<TokenId>) -> Vec<Option<TokenMetadata>> {
+        if token_ids.len() > MAX_QUERY_ITEMS {
+            services::utils::panic(ExtendedError::BatchTooLarge);
+        }
+        token_ids
+            .into_iter()
+            .map(|token_id| self.token_metadata_by_id(token_id))
+            .collect()
+    }
+    /// First page of live tokens, `truncated` is set when more remain
+    pub fn available_nfts(&self) -> AvailableNfts {
+        let nfts = self.available_nfts_page(0, MAX_QUERY_ITEMS as u32);
+        AvailableNfts {
+            truncated: self.get().token_metadata_by_id.len() > nfts.len(),
+            nfts,
+        }
+    }
+    /// Ids of live tokens in ascending order, at most `MAX_QUERY_ITEMS` per page
+    pub fn available_token_ids(&self, offset: u32, limit: u32) -> Vec<TokenId> {
+        let mut token_ids: Vec<TokenId> = self.get().token_metadata_by_id.keys().copied().collect();
+        token_ids.sort();
+        token_ids
+            .into_iter()
+            .skip(offset as usize)
+            .take((limit as usize).min(MAX_QUERY_ITEMS))
+            .collect()
+    }
+    /// Live tokens sorted by id, at most `MAX_QUERY_ITEMS` per page
+    pub fn available_nfts_page(&self, offset: u32, limit: u32) -> Vec<(TokenId, TokenMetadata)> {
+        let mut nfts: Vec<(TokenId, TokenMetadata)> = self
+            .get()
+            .token_metadata_by_id
+            .iter()
+            .map(|(token_id, metadata)| (*token_id, metadata.clone()))
+            .collect();
+        nfts.sort_by_key(|(token_id, _)| *token_id);
+        nfts.into_iter()
+            .skip(offset as usize)
+            .take((limit as usize).min(MAX_QUERY_ITEMS))
+            .collect()
+    }
+    /// Owner's tokens whose name starts with `prefix` (case-sensitive), lowest ids first
+    pub fn find_nfts_by_name_prefix(
+        &self,
+        owner: ActorId,
+        prefix: String,
+        limit: u32,
+    ) -> Vec<(TokenId, TokenMetadata)> {
+        let mut tokens: Vec<(TokenId, TokenMetadata)> = self
+            .tokens_for_owner(owner)
+            .into_iter()
+            .filter(|(_, metadata)| metadata.name.starts_with(prefix.as_str()))
+            .collect();
+        tokens.sort_by_key(|(token_id, _)| *token_id);
+        tokens.truncate(limit as usize);
+        tokens
+    }
+    /// Metadata of a live or burned token, the flag is `true` once burned
+    pub fn token_history(&self, token_id: TokenId) -> Option<(TokenMetadata, bool)> {
+        self.token_metadata_by_id(token_id)
+            .map(|metadata| (metadata, false))
+            .or_else(|| {
+                self.get()
+                    .burned_tokens
+                    .get(&token_id)
+                    .cloned()
+                    .map(|metadata| (metadata, true))
+            })
+    }
+    /// Tells a burned token apart from one that was never minted
+    pub fn token_status(&self, token_id: TokenId) -> TokenStatus {
+        let storage = self.get();
+        if storage.token_metadata_by_id.contains_key(&token_id) {
+            TokenStatus::Live
+        } else if storage.burned_tokens.contains_key(&token_id) {
+            TokenStatus::Burned
+        } else {
+            TokenStatus::NeverExisted
+        }
+    }
+    pub fn transfer_count(&self, token_id: TokenId) -> u32 {
+        self.get()
+            .transfer_counts
+            .get(&token_id)
+            .copied()
+            .unwrap_or_default()
+    }
+    /// Owners of the token from oldest to current, capped at the most recent ones
+    pub fn ownership_history_of(&self, token_id: TokenId) -> Vec<ActorId> {
+        self.get()
+            .ownership_history
+            .get(&token_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+    pub fn is_locked(&self, token_id: TokenId) -> bool {
+        self.get().locked_tokens.contains(&token_id)
+    }
+    pub fn emit_events(&self) -> bool {
+        self.get().emit_events
+    }
+    pub fn minting_paused(&self) -> bool {
+        self.get().minting_paused
+    }
+    /// Ids the owner burned, oldest first, capped at the most recent ones
+    pub fn retired_tokens_of(&self, owner: ActorId) -> Vec<TokenId> {
+        self.get().retired.get(&owner).cloned().unwrap_or_default()
+    }
+    pub fn type_tag(&self, token_id: TokenId) -> Option<String> {
+        self.get().type_tags.get(&token_id).cloned()
+    }
+    pub fn is_metadata_frozen(&self, token_id: TokenId) -> bool {
+        self.get().frozen_metadata.contains(&token_id)
+    }
+    pub fn is_soulbound(&self, token_id: TokenId) -> bool {
+        self.get().soulbound.contains(&token_id)
+    }
+    pub fn token_blocks(&self, token_id: TokenId) -> Option<TokenBlocks> {
+        self.get().token_blocks.get(&token_id).copied()
+    }
     pub fn tokens_for_owner(&self, owner: ActorId) -> Vec<(TokenId, TokenMetadata)> {
         Storage::tokens_for_owner()
             .get(&owner)
@@ -176,6 +854,10 @@ impl ExtendedService {
             })
             .collect()
     }
+    /// The caller's live tokens, same as `tokens_for_owner(msg::source())`
+    pub fn my_nfts(&self) -> Vec<(TokenId, TokenMetadata)> {
+        self.tokens_for_owner(msg::source())
+    }
 }
 
 impl ExtendedService {
@@ -184,6 +866,171 @@ impl ExtendedService {
             panic!("Not admin")
         };
     }
+    fn notify(&mut self, event: Event) {
+        if self.get().emit_events {
+            self.emit_event(event).expect("Notification Error");
+        }
+    }
+    fn next_event_seq(&mut self) -> u64 {
+        let storage = self.get_mut();
+        storage.event_seq = storage
+            .event_seq
+            .checked_add(1)
+            .unwrap_or_else(|| services::utils::panic(ExtendedError::Overflow));
+        storage.event_seq
+    }
+    fn ensure_not_zero(to: ActorId) {
+        if to == ActorId::zero() {
+            services::utils::panic(ExtendedError::ZeroAddress)
+        }
+    }
+    fn ensure_minting_enabled(&self) {
+        if self.get().minting_paused {
+            services::utils::panic(ExtendedError::MintingPaused)
+        }
+    }
+    /// Counts mints in the current window and pauses minting once the threshold is exceeded,
+    /// the call that trips the breaker still goes through so the pause isn't reverted
+    fn record_mint_burst(&mut self, minted: u32) {
+        let block = exec::block_height();
+        let storage = self.get_mut();
+        if block >= storage.mint_window_start.saturating_add(MINT_WINDOW_BLOCKS) {
+            storage.mint_window_start = block;
+            storage.mints_in_window = 0;
+        }
+        storage.mints_in_window = storage.mints_in_window.saturating_add(minted);
+        let tripped = storage
+            .mint_burst_threshold
+            .is_some_and(|threshold| storage.mints_in_window > threshold);
+        if tripped {
+            storage.minting_paused = true;
+            let mints_in_window = storage.mints_in_window;
+            let seq = self.next_event_seq();
+            self.notify(Event::AutoPaused {
+                mints_in_window,
+                seq,
+            });
+        }
+    }
+    fn ensure_transferable(&self, token_id: TokenId) {
+        if self.get().soulbound.contains(&token_id) {
+            services::utils::panic(ExtendedError::Soulbound)
+        }
+        self.ensure_not_locked(token_id);
+    }
+    fn ensure_not_locked(&self, token_id: TokenId) {
+        if self.get().locked_tokens.contains(&token_id) {
+            services::utils::panic(ExtendedError::Locked)
+        }
+    }
+    fn validate_metadata(&self, token_metadata: &TokenMetadata) -> Result<(), ExtendedError> {
+        if token_metadata.name.len() > MAX_NAME_LEN
+            || token_metadata.description.len() > MAX_DESCRIPTION_LEN
+            || token_metadata.media.len() > MAX_URL_LEN
+            || token_metadata.reference.len() > MAX_URL_LEN
+        {
+            return Err(ExtendedError::InvalidMetadata);
+        }
+        let schemes = &self.get().allowed_schemes;
+        if !schemes.is_empty()
+            && !schemes
+                .iter()
+                .any(|scheme| token_metadata.media.starts_with(scheme.as_str()))
+        {
+            return Err(ExtendedError::InvalidMetadata);
+        }
+        Ok(())
+    }
+    fn record_transfer(&mut self, from: ActorId, to: ActorId, token_id: TokenId, initiator: ActorId) {
+        self.get_mut()
+            .transfer_counts
+            .entry(token_id)
+            .and_modify(|count| *count = count.saturating_add(1))
+            .or_insert(1);
+        let history = self.get_mut().ownership_history.entry(token_id).or_default();
+        if history.len() >= MAX_OWNERSHIP_HISTORY {
+            history.remove(0);
+        }
+        history.push(to);
+        let seq = self.next_event_seq();
+        self.notify(Event::Transferred {
+            from,
+            to,
+            token_id,
+            initiator,
+            seq,
+        });
+    }
+    fn mint_token(&mut self, to: ActorId, token_metadata: TokenMetadata) -> TokenId {
+        let token_id = self.get().token_id;
+        services::utils::panicking(|| {
+            funcs::mint(
+                Storage::owner_by_id(),
+                Storage::tokens_for_owner(),
+                &mut self.get_mut().token_metadata_by_id,
+                &mut self.get_mut().token_id,
+                to,
+                token_metadata.clone(),
+            )
+        });
+        self.record_minted(to, token_id);
+        let seq = self.next_event_seq();
+        self.notify(Event::Minted {
+            to,
+            token_metadata,
+            token_id,
+            seq,
+        });
+        self.check_id_space();
+        token_id
+    }
+    /// Advisory only: emits `IdSpaceWarning` the first time the next id crosses the threshold
+    fn check_id_space(&mut self) {
+        let storage = self.get_mut();
+        let next = storage.token_id;
+        let crossed = storage
+            .id_warning_threshold
+            .is_some_and(|threshold| next >= threshold);
+        if crossed && !storage.id_warning_emitted {
+            storage.id_warning_emitted = true;
+            let remaining = TokenId::MAX - next;
+            let seq = self.next_event_seq();
+            self.notify(Event::IdSpaceWarning { remaining, seq });
+        }
+    }
+    fn record_burned(&mut self, owner: ActorId, token_id: TokenId) {
+        let storage = self.get_mut();
+        storage.token_blocks.remove(&token_id);
+        storage.soulbound.remove(&token_id);
+        storage.type_tags.remove(&token_id);
+        storage.frozen_metadata.remove(&token_id);
+        let retired = storage.retired.entry(owner).or_default();
+        if retired.len() >= MAX_RETIRED_PER_OWNER {
+            retired.remove(0);
+        }
+        retired.push(token_id);
+    }
+    fn record_minted(&mut self, to: ActorId, token_id: TokenId) {
+        self.get_mut().transfer_counts.remove(&token_id);
+        self.get_mut().ownership_history.insert(token_id, vec![to]);
+        let block = exec::block_height();
+        self.get_mut().token_blocks.insert(
+            token_id,
+            TokenBlocks {
+                created_at: block,
+                last_updated_at: block,
+            },
+        );
+    }
+    fn ensure_is_main_contract(&self) {
+        match self.get().main_contract {
+            None => panic!("Main contract is not set"),
+            Some(main_contract) if main_contract != msg::source() => {
+                panic!("Not main contract")
+            }
+            _ => {}
+        }
+    }
 }
 impl AsRef<VnftService> for ExtendedService {
     fn as_ref(&self) -> &VnftService {
@@ -1,13 +1,47 @@
 use extended_vnft_client::{
     traits::{ExtendedVnftFactory, Vnft},
-    ExtendedVnftFactory as Factory, TokenMetadata, Vnft as VftClient,
+    vnft::events::{self as vnft_events, VnftEvents},
+    ExtendedError, ExtendedVnftFactory as Factory, TokenMetadata, TokenStatus, Vnft as VftClient,
 };
 use sails_rs::calls::*;
+use sails_rs::events::*;
+use sails_rs::futures::StreamExt;
 use sails_rs::gtest::{calls::*, System};
+use sails_rs::ActorId;
 
 pub const ADMIN_ID: u64 = 10;
 pub const USER_ID: [u64; 2] = [11, 12];
 
+async fn deploy() -> (GTestRemoting, ActorId) {
+    let system = System::new();
+    system.init_logger();
+    system.mint_to(ADMIN_ID, 1_000_000_000_000_000);
+    system.mint_to(USER_ID[0], 1_000_000_000_000_000);
+    system.mint_to(USER_ID[1], 1_000_000_000_000_000);
+    let program_space = GTestRemoting::new(system, ADMIN_ID.into());
+
+    let code_id = program_space
+        .system()
+        .submit_code_file("../target/wasm32-gear/release/extended_vnft.opt.wasm");
+
+    let extended_vnft_id = Factory::new(program_space.clone())
+        .new("name".to_string(), "symbol".to_string())
+        .send_recv(code_id, "123")
+        .await
+        .unwrap();
+
+    (program_space, extended_vnft_id)
+}
+
+fn token_metadata(name: &str) -> TokenMetadata {
+    TokenMetadata {
+        name: name.to_string(),
+        description: "token_description".to_string(),
+        media: "token_media".to_string(),
+        reference: "token_reference".to_string(),
+    }
+}
+
 #[tokio::test]
 async fn test_basic_function() {
     let system = System::new();
@@ -249,3 +283,1375 @@ async fn test_grant_role() {
     let burners = client.burners().recv(extended_vft_id).await.unwrap();
     assert_eq!(burners, vec![ADMIN_ID.into()]);
 }
+
+#[tokio::test]
+async fn test_minted_event_carries_metadata() {
+    let (program_space, extended_vnft_id) = deploy().await;
+    let mut client = VftClient::new(program_space.clone());
+    let mut listener = vnft_events::listener(program_space);
+    let mut events = listener.listen().await.unwrap();
+
+    client
+        .mint(USER_ID[0].into(), token_metadata("sword"))
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+
+    let event = events.next().await.unwrap();
+    assert_eq!(
+        event,
+        (
+            extended_vnft_id,
+            VnftEvents::Minted {
+                to: USER_ID[0].into(),
+                token_metadata: token_metadata("sword"),
+                token_id: 0.into(),
+                seq: 1,
+            }
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_admin_mint_with_id() {
+    let (program_space, extended_vnft_id) = deploy().await;
+    let mut client = VftClient::new(program_space);
+
+    for id in [5, 2, 9] {
+        client
+            .admin_mint_with_id(USER_ID[0].into(), id.into(), token_metadata("migrated"))
+            .send_recv(extended_vnft_id)
+            .await
+            .unwrap();
+    }
+    // existing id is rejected
+    let res = client
+        .admin_mint_with_id(USER_ID[1].into(), 5.into(), token_metadata("migrated"))
+        .send_recv(extended_vnft_id)
+        .await;
+    assert!(res.is_err());
+    // only admins can migrate
+    let res = client
+        .admin_mint_with_id(USER_ID[1].into(), 20.into(), token_metadata("migrated"))
+        .with_args(|args| args.with_actor_id(USER_ID[1].into()))
+        .send_recv(extended_vnft_id)
+        .await;
+    assert!(res.is_err());
+
+    client
+        .mint(USER_ID[1].into(), token_metadata("fresh"))
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+    let owner = client
+        .owner_of(10.into())
+        .recv(extended_vnft_id)
+        .await
+        .unwrap();
+    assert_eq!(owner, USER_ID[1].into());
+    let token_id = client.token_id().recv(extended_vnft_id).await.unwrap();
+    assert_eq!(token_id, 11.into());
+}
+
+#[tokio::test]
+async fn test_main_transfer() {
+    let (program_space, extended_vnft_id) = deploy().await;
+    let mut client = VftClient::new(program_space);
+
+    client
+        .mint(USER_ID[0].into(), token_metadata("loot"))
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+    // no main contract configured yet
+    let res = client
+        .main_transfer(ADMIN_ID.into(), 0.into())
+        .with_args(|args| args.with_actor_id(USER_ID[1].into()))
+        .send_recv(extended_vnft_id)
+        .await;
+    assert!(res.is_err());
+
+    client
+        .set_main_contract(USER_ID[1].into())
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap()
+        .unwrap();
+    // a random account can't move the token
+    let res = client
+        .main_transfer(ADMIN_ID.into(), 0.into())
+        .send_recv(extended_vnft_id)
+        .await;
+    assert!(res.is_err());
+
+    client
+        .main_transfer(ADMIN_ID.into(), 0.into())
+        .with_args(|args| args.with_actor_id(USER_ID[1].into()))
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+    let owner = client
+        .owner_of(0.into())
+        .recv(extended_vnft_id)
+        .await
+        .unwrap();
+    assert_eq!(owner, ADMIN_ID.into());
+}
+
+#[tokio::test]
+async fn test_unauthorized_attempt_breadcrumb() {
+    let (program_space, extended_vnft_id) = deploy().await;
+    let mut client = VftClient::new(program_space.clone());
+    let mut listener = vnft_events::listener(program_space);
+    let mut events = listener.listen().await.unwrap();
+
+    let res = client
+        .set_main_contract(USER_ID[0].into())
+        .with_args(|args| args.with_actor_id(USER_ID[0].into()))
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+    assert_eq!(res, Err(ExtendedError::Unauthorized));
+
+    let event = events.next().await.unwrap();
+    assert_eq!(
+        event,
+        (
+            extended_vnft_id,
+            VnftEvents::UnauthorizedAttempt {
+                caller: USER_ID[0].into(),
+                action: "set_main_contract".to_string(),
+                seq: 1,
+            }
+        )
+    );
+    let main_contract = client.main_contract().recv(extended_vnft_id).await.unwrap();
+    assert_eq!(main_contract, None);
+}
+
+#[tokio::test]
+async fn test_burn_batch() {
+    let (program_space, extended_vnft_id) = deploy().await;
+    let mut client = VftClient::new(program_space);
+
+    for to in [USER_ID[0], USER_ID[0], USER_ID[1], USER_ID[0]] {
+        client
+            .mint(to.into(), token_metadata("junk"))
+            .send_recv(extended_vnft_id)
+            .await
+            .unwrap();
+    }
+    // the third id belongs to someone else, nothing gets burned
+    let res = client
+        .burn_batch(vec![0.into(), 1.into(), 2.into()])
+        .with_args(|args| args.with_actor_id(USER_ID[0].into()))
+        .send_recv(extended_vnft_id)
+        .await;
+    assert!(res.is_err());
+    let balance = client
+        .balance_of(USER_ID[0].into())
+        .recv(extended_vnft_id)
+        .await
+        .unwrap();
+    assert_eq!(balance, 3.into());
+
+    // a repeated id is rejected before the first copy is burned
+    let res = client
+        .burn_batch(vec![0.into(), 1.into(), 0.into()])
+        .with_args(|args| args.with_actor_id(USER_ID[0].into()))
+        .send_recv(extended_vnft_id)
+        .await;
+    assert!(res.is_err());
+    let balance = client
+        .balance_of(USER_ID[0].into())
+        .recv(extended_vnft_id)
+        .await
+        .unwrap();
+    assert_eq!(balance, 3.into());
+
+    client
+        .burn_batch(vec![0.into(), 1.into(), 3.into()])
+        .with_args(|args| args.with_actor_id(USER_ID[0].into()))
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+    let balance = client
+        .balance_of(USER_ID[0].into())
+        .recv(extended_vnft_id)
+        .await
+        .unwrap();
+    assert_eq!(balance, 0.into());
+    let balance = client
+        .balance_of(USER_ID[1].into())
+        .recv(extended_vnft_id)
+        .await
+        .unwrap();
+    assert_eq!(balance, 1.into());
+}
+
+#[tokio::test]
+async fn test_token_blocks() {
+    let (program_space, extended_vnft_id) = deploy().await;
+    let mut client = VftClient::new(program_space);
+
+    for _ in 0..2 {
+        client
+            .mint(USER_ID[0].into(), token_metadata("item"))
+            .send_recv(extended_vnft_id)
+            .await
+            .unwrap();
+    }
+    let first = client
+        .token_blocks(0.into())
+        .recv(extended_vnft_id)
+        .await
+        .unwrap()
+        .unwrap();
+    let second = client
+        .token_blocks(1.into())
+        .recv(extended_vnft_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(first.created_at, first.last_updated_at);
+    assert!(second.created_at > first.created_at);
+
+    client
+        .burn(USER_ID[0].into(), 0.into())
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+    let blocks = client
+        .token_blocks(0.into())
+        .recv(extended_vnft_id)
+        .await
+        .unwrap();
+    assert!(blocks.is_none());
+}
+
+#[tokio::test]
+async fn test_clear_main_contract() {
+    let (program_space, extended_vnft_id) = deploy().await;
+    let mut client = VftClient::new(program_space);
+
+    client
+        .mint(USER_ID[0].into(), token_metadata("loot"))
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+    client
+        .set_main_contract(USER_ID[1].into())
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap()
+        .unwrap();
+    client
+        .clear_main_contract()
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+    let main_contract = client.main_contract().recv(extended_vnft_id).await.unwrap();
+    assert_eq!(main_contract, None);
+
+    let res = client
+        .main_transfer(ADMIN_ID.into(), 0.into())
+        .with_args(|args| args.with_actor_id(USER_ID[1].into()))
+        .send_recv(extended_vnft_id)
+        .await;
+    assert!(res.is_err());
+}
+
+#[tokio::test]
+async fn test_allowed_media_schemes() {
+    let (program_space, extended_vnft_id) = deploy().await;
+    let mut client = VftClient::new(program_space);
+
+    client
+        .set_allowed_schemes(vec!["https://".to_string(), "ipfs://".to_string()])
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+
+    let mut metadata = token_metadata("item");
+    metadata.media = "http://example.com/item.png".to_string();
+    let res = client
+        .mint(USER_ID[0].into(), metadata.clone())
+        .send_recv(extended_vnft_id)
+        .await;
+    assert!(res.is_err());
+
+    metadata.media = "ipfs://bafy".to_string();
+    client
+        .mint(USER_ID[0].into(), metadata)
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+    let balance = client
+        .balance_of(USER_ID[0].into())
+        .recv(extended_vnft_id)
+        .await
+        .unwrap();
+    assert_eq!(balance, 1.into());
+}
+
+#[tokio::test]
+async fn test_burned_token_history() {
+    let (program_space, extended_vnft_id) = deploy().await;
+    let mut client = VftClient::new(program_space);
+
+    for name in ["old", "new"] {
+        client
+            .mint(USER_ID[0].into(), token_metadata(name))
+            .send_recv(extended_vnft_id)
+            .await
+            .unwrap();
+    }
+    client
+        .burn(USER_ID[0].into(), 0.into())
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+
+    let history = client
+        .token_history(0.into())
+        .recv(extended_vnft_id)
+        .await
+        .unwrap();
+    assert_eq!(history, Some((token_metadata("old"), true)));
+    let history = client
+        .token_history(1.into())
+        .recv(extended_vnft_id)
+        .await
+        .unwrap();
+    assert_eq!(history, Some((token_metadata("new"), false)));
+
+    let tokens = client
+        .tokens_for_owner(USER_ID[0].into())
+        .recv(extended_vnft_id)
+        .await
+        .unwrap();
+    assert_eq!(tokens, vec![(1.into(), token_metadata("new"))]);
+    let metadata = client
+        .token_metadata_by_id(0.into())
+        .recv(extended_vnft_id)
+        .await
+        .unwrap();
+    assert!(metadata.is_none());
+}
+
+#[tokio::test]
+async fn test_transfer_initiator() {
+    let (program_space, extended_vnft_id) = deploy().await;
+    let mut client = VftClient::new(program_space.clone());
+    let mut listener = vnft_events::listener(program_space);
+    let mut events = listener.listen().await.unwrap();
+
+    client
+        .mint(ADMIN_ID.into(), token_metadata("item"))
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+    // owner transfer
+    client
+        .transfer(USER_ID[0].into(), 0.into())
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+    let initiator = loop {
+        if let (_, VnftEvents::Transferred { initiator, .. }) = events.next().await.unwrap() {
+            break initiator;
+        }
+    };
+    assert_eq!(initiator, ADMIN_ID.into());
+
+    // operator transfer
+    client
+        .approve(USER_ID[1].into(), 0.into())
+        .with_args(|args| args.with_actor_id(USER_ID[0].into()))
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+    client
+        .transfer_from(USER_ID[0].into(), ADMIN_ID.into(), 0.into())
+        .with_args(|args| args.with_actor_id(USER_ID[1].into()))
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+    let initiator = loop {
+        if let (_, VnftEvents::Transferred { initiator, .. }) = events.next().await.unwrap() {
+            break initiator;
+        }
+    };
+    assert_eq!(initiator, USER_ID[1].into());
+}
+
+#[tokio::test]
+async fn test_multiple_seed_admins() {
+    let system = System::new();
+    system.init_logger();
+    system.mint_to(ADMIN_ID, 1_000_000_000_000_000);
+    system.mint_to(USER_ID[0], 1_000_000_000_000_000);
+    system.mint_to(USER_ID[1], 1_000_000_000_000_000);
+    let program_space = GTestRemoting::new(system, ADMIN_ID.into());
+
+    let code_id = program_space
+        .system()
+        .submit_code_file("../target/wasm32-gear/release/extended_vnft.opt.wasm");
+
+    let factory = Factory::new(program_space.clone());
+    // an empty admin list is rejected
+    let res = factory
+        .new_with_admins("name".to_string(), "symbol".to_string(), vec![])
+        .send_recv(code_id, "123")
+        .await;
+    assert!(res.is_err());
+
+    let extended_vnft_id = factory
+        .new_with_admins(
+            "name".to_string(),
+            "symbol".to_string(),
+            vec![USER_ID[0].into(), USER_ID[1].into()],
+        )
+        .send_recv(code_id, "456")
+        .await
+        .unwrap();
+
+    let mut client = VftClient::new(program_space);
+    for admin in USER_ID {
+        client
+            .mint(admin.into(), token_metadata("item"))
+            .with_args(|args| args.with_actor_id(admin.into()))
+            .send_recv(extended_vnft_id)
+            .await
+            .unwrap();
+    }
+    // the deployer isn't an admin here
+    let res = client
+        .mint(ADMIN_ID.into(), token_metadata("item"))
+        .send_recv(extended_vnft_id)
+        .await;
+    assert!(res.is_err());
+}
+
+#[tokio::test]
+async fn test_zero_address_rejected() {
+    let (program_space, extended_vnft_id) = deploy().await;
+    let mut client = VftClient::new(program_space);
+
+    let res = client
+        .mint(ActorId::zero(), token_metadata("item"))
+        .send_recv(extended_vnft_id)
+        .await;
+    assert!(res.is_err());
+    let res = client
+        .admin_mint_with_id(ActorId::zero(), 7.into(), token_metadata("item"))
+        .send_recv(extended_vnft_id)
+        .await;
+    assert!(res.is_err());
+
+    client
+        .mint(ADMIN_ID.into(), token_metadata("item"))
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+    let res = client
+        .transfer(ActorId::zero(), 0.into())
+        .send_recv(extended_vnft_id)
+        .await;
+    assert!(res.is_err());
+    let owner = client
+        .owner_of(0.into())
+        .recv(extended_vnft_id)
+        .await
+        .unwrap();
+    assert_eq!(owner, ADMIN_ID.into());
+}
+
+#[tokio::test]
+async fn test_transfer_count() {
+    let (program_space, extended_vnft_id) = deploy().await;
+    let mut client = VftClient::new(program_space);
+
+    client
+        .mint(ADMIN_ID.into(), token_metadata("item"))
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+    let count = client
+        .transfer_count(0.into())
+        .recv(extended_vnft_id)
+        .await
+        .unwrap();
+    assert_eq!(count, 0);
+
+    client
+        .transfer(USER_ID[0].into(), 0.into())
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+    client
+        .transfer(USER_ID[1].into(), 0.into())
+        .with_args(|args| args.with_actor_id(USER_ID[0].into()))
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+    let count = client
+        .transfer_count(0.into())
+        .recv(extended_vnft_id)
+        .await
+        .unwrap();
+    assert_eq!(count, 2);
+}
+
+#[tokio::test]
+async fn test_version() {
+    let (program_space, extended_vnft_id) = deploy().await;
+    let client = VftClient::new(program_space);
+
+    let version = client.version().recv(extended_vnft_id).await.unwrap();
+    assert_eq!(version, 11);
+}
+
+#[tokio::test]
+async fn test_query_state_at() {
+    let (program_space, extended_vnft_id) = deploy().await;
+    let mut client = VftClient::new(program_space);
+
+    let (state, first_height) = client.query_state_at().recv(extended_vnft_id).await.unwrap();
+    assert_eq!(state.next_token_id, 0.into());
+    assert_eq!(state.admins, vec![ActorId::from(ADMIN_ID)]);
+    assert_eq!(state.main_contract, None);
+    assert!(state.emit_events);
+    assert!(!state.minting_paused);
+    assert_eq!(state.live_tokens, 0);
+    assert_ne!(first_height, 0);
+
+    client
+        .mint(USER_ID[0].into(), token_metadata("token_name"))
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+    client
+        .mint(USER_ID[0].into(), token_metadata("token_name"))
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+    client
+        .burn(USER_ID[0].into(), 0.into())
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+    let (state, height) = client.query_state_at().recv(extended_vnft_id).await.unwrap();
+    assert_eq!(state.next_token_id, 2.into());
+    assert_eq!((state.live_tokens, state.burned_tokens), (1, 1));
+    assert!(height > first_height);
+}
+
+#[tokio::test]
+async fn test_event_seq() {
+    let (program_space, extended_vnft_id) = deploy().await;
+    let mut client = VftClient::new(program_space.clone());
+    let mut listener = vnft_events::listener(program_space);
+    let mut events = listener.listen().await.unwrap();
+
+    for _ in 0..2 {
+        client
+            .mint(ADMIN_ID.into(), token_metadata("item"))
+            .send_recv(extended_vnft_id)
+            .await
+            .unwrap();
+    }
+    client
+        .transfer(USER_ID[0].into(), 0.into())
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+
+    let mut seqs = Vec::new();
+    while seqs.len() < 3 {
+        match events.next().await.unwrap().1 {
+            VnftEvents::Minted { seq, .. } | VnftEvents::Transferred { seq, .. } => seqs.push(seq),
+            _ => {}
+        }
+    }
+    assert_eq!(seqs, vec![1, 2, 3]);
+}
+
+#[tokio::test]
+async fn test_find_nfts_by_name_prefix() {
+    let (program_space, extended_vnft_id) = deploy().await;
+    let mut client = VftClient::new(program_space);
+
+    for name in ["Sword of Dawn", "Shield", "Sword of Dusk", "sword (rusty)", "Sword of Ash"] {
+        client
+            .mint(USER_ID[0].into(), token_metadata(name))
+            .send_recv(extended_vnft_id)
+            .await
+            .unwrap();
+    }
+
+    let found = client
+        .find_nfts_by_name_prefix(USER_ID[0].into(), "Sword".to_string(), 10)
+        .recv(extended_vnft_id)
+        .await
+        .unwrap();
+    let ids: Vec<_> = found.iter().map(|(id, _)| *id).collect();
+    assert_eq!(ids, vec![0.into(), 2.into(), 4.into()]);
+
+    let found = client
+        .find_nfts_by_name_prefix(USER_ID[0].into(), "Sword".to_string(), 2)
+        .recv(extended_vnft_id)
+        .await
+        .unwrap();
+    assert_eq!(found.len(), 2);
+}
+
+#[tokio::test]
+async fn test_mint_batch_by_main_contract() {
+    let (program_space, extended_vnft_id) = deploy().await;
+    let mut client = VftClient::new(program_space);
+
+    let rewards: Vec<_> = (0..5)
+        .map(|i| token_metadata(&format!("reward {i}")))
+        .collect();
+    // neither a minter nor the main contract
+    let res = client
+        .mint_batch(USER_ID[0].into(), rewards.clone())
+        .with_args(|args| args.with_actor_id(USER_ID[1].into()))
+        .send_recv(extended_vnft_id)
+        .await;
+    assert!(res.is_err());
+
+    client
+        .set_main_contract(USER_ID[1].into())
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap()
+        .unwrap();
+    let ids = client
+        .mint_batch(USER_ID[0].into(), rewards)
+        .with_args(|args| args.with_actor_id(USER_ID[1].into()))
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+    assert_eq!(ids, (0..5).map(Into::into).collect::<Vec<_>>());
+
+    let balance = client
+        .balance_of(USER_ID[0].into())
+        .recv(extended_vnft_id)
+        .await
+        .unwrap();
+    assert_eq!(balance, 5.into());
+}
+
+#[tokio::test]
+async fn test_soulbound() {
+    let (program_space, extended_vnft_id) = deploy().await;
+    let mut client = VftClient::new(program_space);
+
+    client
+        .mint_soulbound(ADMIN_ID.into(), token_metadata("badge"))
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+    let soulbound = client
+        .is_soulbound(0.into())
+        .recv(extended_vnft_id)
+        .await
+        .unwrap();
+    assert!(soulbound);
+
+    let res = client
+        .transfer(USER_ID[0].into(), 0.into())
+        .send_recv(extended_vnft_id)
+        .await;
+    assert!(res.is_err());
+
+    client
+        .burn_batch(vec![0.into()])
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+    let balance = client
+        .balance_of(ADMIN_ID.into())
+        .recv(extended_vnft_id)
+        .await
+        .unwrap();
+    assert_eq!(balance, 0.into());
+}
+
+#[tokio::test]
+async fn test_ownership_history() {
+    let (program_space, extended_vnft_id) = deploy().await;
+    let mut client = VftClient::new(program_space);
+
+    client
+        .mint(ADMIN_ID.into(), token_metadata("relic"))
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+    client
+        .transfer(USER_ID[0].into(), 0.into())
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+    client
+        .transfer(USER_ID[1].into(), 0.into())
+        .with_args(|args| args.with_actor_id(USER_ID[0].into()))
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+
+    let history = client
+        .ownership_history_of(0.into())
+        .recv(extended_vnft_id)
+        .await
+        .unwrap();
+    assert_eq!(
+        history,
+        vec![ADMIN_ID.into(), USER_ID[0].into(), USER_ID[1].into()]
+    );
+}
+
+#[tokio::test]
+async fn test_available_nfts_truncated() {
+    let (program_space, extended_vnft_id) = deploy().await;
+    let mut client = VftClient::new(program_space);
+
+    for _ in 0..3 {
+        client
+            .mint_batch(ADMIN_ID.into(), vec![token_metadata("item"); 50])
+            .send_recv(extended_vnft_id)
+            .await
+            .unwrap();
+    }
+
+    let available = client
+        .available_nfts()
+        .recv(extended_vnft_id)
+        .await
+        .unwrap();
+    assert!(available.truncated);
+    assert_eq!(available.nfts.len(), 100);
+
+    let rest = client
+        .available_nfts_page(100, 100)
+        .recv(extended_vnft_id)
+        .await
+        .unwrap();
+    assert_eq!(rest.len(), 50);
+    assert_eq!(rest[0].0, 100.into());
+}
+
+#[tokio::test]
+async fn test_set_description() {
+    let (program_space, extended_vnft_id) = deploy().await;
+    let mut client = VftClient::new(program_space);
+
+    client
+        .mint(USER_ID[0].into(), token_metadata("cloak"))
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+    // only the owner can annotate
+    let res = client
+        .set_description(0.into(), "mine now".to_string())
+        .send_recv(extended_vnft_id)
+        .await;
+    assert!(res.is_err());
+    let res = client
+        .set_description(0.into(), "x".repeat(1025))
+        .with_args(|args| args.with_actor_id(USER_ID[0].into()))
+        .send_recv(extended_vnft_id)
+        .await;
+    assert!(res.is_err());
+
+    client
+        .set_description(0.into(), "found in the crypt".to_string())
+        .with_args(|args| args.with_actor_id(USER_ID[0].into()))
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+    let metadata = client
+        .token_metadata_by_id(0.into())
+        .recv(extended_vnft_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(metadata.description, "found in the crypt");
+    assert_eq!(metadata.name, "cloak");
+}
+
+#[tokio::test]
+async fn test_tokens_by_ids() {
+    let (program_space, extended_vnft_id) = deploy().await;
+    let mut client = VftClient::new(program_space);
+
+    for name in ["helm", "boots"] {
+        client
+            .mint(USER_ID[0].into(), token_metadata(name))
+            .send_recv(extended_vnft_id)
+            .await
+            .unwrap();
+    }
+
+    let tokens = client
+        .tokens_by_ids(vec![1.into(), 7.into(), 0.into()])
+        .recv(extended_vnft_id)
+        .await
+        .unwrap();
+    assert_eq!(
+        tokens,
+        vec![Some(token_metadata("boots")), None, Some(token_metadata("helm"))]
+    );
+}
+
+#[tokio::test]
+async fn test_mint_circuit_breaker() {
+    let (program_space, extended_vnft_id) = deploy().await;
+    let mut client = VftClient::new(program_space);
+
+    client
+        .set_mint_burst_threshold(Some(3))
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+    for _ in 0..4 {
+        client
+            .mint(ADMIN_ID.into(), token_metadata("item"))
+            .send_recv(extended_vnft_id)
+            .await
+            .unwrap();
+    }
+    // the fourth mint in the window tripped the breaker
+    let paused = client
+        .minting_paused()
+        .recv(extended_vnft_id)
+        .await
+        .unwrap();
+    assert!(paused);
+    let res = client
+        .mint(ADMIN_ID.into(), token_metadata("item"))
+        .send_recv(extended_vnft_id)
+        .await;
+    assert!(res.is_err());
+
+    client
+        .unpause_minting()
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+    client
+        .mint(ADMIN_ID.into(), token_metadata("item"))
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_available_token_ids() {
+    let (program_space, extended_vnft_id) = deploy().await;
+    let mut client = VftClient::new(program_space);
+
+    client
+        .mint_batch(USER_ID[0].into(), vec![token_metadata("item"); 4])
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+    client
+        .burn(USER_ID[0].into(), 1.into())
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+
+    let ids = client
+        .available_token_ids(0, u32::MAX)
+        .recv(extended_vnft_id)
+        .await
+        .unwrap();
+    assert_eq!(ids, vec![0.into(), 2.into(), 3.into()]);
+    let available = client
+        .available_nfts()
+        .recv(extended_vnft_id)
+        .await
+        .unwrap();
+    let listed: Vec<_> = available.nfts.iter().map(|(id, _)| *id).collect();
+    assert_eq!(ids, listed);
+    let page = client
+        .available_token_ids(1, 1)
+        .recv(extended_vnft_id)
+        .await
+        .unwrap();
+    assert_eq!(page, vec![2.into()]);
+
+    // a single reply never carries more than 100 ids
+    for _ in 0..2 {
+        client
+            .mint_batch(USER_ID[0].into(), vec![token_metadata("item"); 50])
+            .send_recv(extended_vnft_id)
+            .await
+            .unwrap();
+    }
+    let ids = client
+        .available_token_ids(0, u32::MAX)
+        .recv(extended_vnft_id)
+        .await
+        .unwrap();
+    assert_eq!(ids.len(), 100);
+    let rest = client
+        .available_token_ids(100, u32::MAX)
+        .recv(extended_vnft_id)
+        .await
+        .unwrap();
+    assert_eq!(rest.len(), 3);
+}
+
+#[tokio::test]
+async fn test_release_all_locks() {
+    let (program_space, extended_vnft_id) = deploy().await;
+    let mut client = VftClient::new(program_space);
+
+    client
+        .mint_batch(USER_ID[0].into(), vec![token_metadata("item"); 3])
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+    client
+        .set_main_contract(USER_ID[1].into())
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap()
+        .unwrap();
+    for token_id in 0..3 {
+        client
+            .lock_token(token_id.into())
+            .with_args(|args| args.with_actor_id(USER_ID[1].into()))
+            .send_recv(extended_vnft_id)
+            .await
+            .unwrap();
+    }
+    let res = client
+        .transfer(ADMIN_ID.into(), 0.into())
+        .with_args(|args| args.with_actor_id(USER_ID[0].into()))
+        .send_recv(extended_vnft_id)
+        .await;
+    assert!(res.is_err());
+
+    // only admins can release
+    let res = client
+        .admin_release_all_locks()
+        .with_args(|args| args.with_actor_id(USER_ID[1].into()))
+        .send_recv(extended_vnft_id)
+        .await;
+    assert!(res.is_err());
+    client
+        .admin_release_all_locks()
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+
+    for token_id in 0..3 {
+        let locked = client
+            .is_locked(token_id.into())
+            .recv(extended_vnft_id)
+            .await
+            .unwrap();
+        assert!(!locked);
+    }
+    client
+        .transfer(ADMIN_ID.into(), 0.into())
+        .with_args(|args| args.with_actor_id(USER_ID[0].into()))
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_my_nfts() {
+    let (program_space, extended_vnft_id) = deploy().await;
+    let mut client = VftClient::new(program_space);
+
+    for name in ["ring", "amulet"] {
+        client
+            .mint(USER_ID[0].into(), token_metadata(name))
+            .send_recv(extended_vnft_id)
+            .await
+            .unwrap();
+    }
+    client
+        .mint(USER_ID[1].into(), token_metadata("dagger"))
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+
+    let mut mine = client
+        .my_nfts()
+        .with_args(|args| args.with_actor_id(USER_ID[0].into()))
+        .recv(extended_vnft_id)
+        .await
+        .unwrap();
+    let mut owned = client
+        .tokens_for_owner(USER_ID[0].into())
+        .recv(extended_vnft_id)
+        .await
+        .unwrap();
+    mine.sort_by_key(|(id, _)| *id);
+    owned.sort_by_key(|(id, _)| *id);
+    assert_eq!(mine.len(), 2);
+    assert_eq!(mine, owned);
+}
+
+#[tokio::test]
+async fn test_token_status() {
+    let (program_space, extended_vnft_id) = deploy().await;
+    let mut client = VftClient::new(program_space);
+
+    client
+        .mint_batch(USER_ID[0].into(), vec![token_metadata("item"); 2])
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+    client
+        .burn(USER_ID[0].into(), 1.into())
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+
+    for (token_id, expected) in [
+        (0, TokenStatus::Live),
+        (1, TokenStatus::Burned),
+        (2, TokenStatus::NeverExisted),
+    ] {
+        let status = client
+            .token_status(token_id.into())
+            .recv(extended_vnft_id)
+            .await
+            .unwrap();
+        assert_eq!(status, expected);
+    }
+}
+
+#[tokio::test]
+async fn test_metadata_length_limits() {
+    let (program_space, extended_vnft_id) = deploy().await;
+    let mut client = VftClient::new(program_space);
+
+    let mut oversized = Vec::new();
+    let mut metadata = token_metadata("item");
+    metadata.name = "n".repeat(129);
+    oversized.push(metadata);
+    let mut metadata = token_metadata("item");
+    metadata.description = "d".repeat(1025);
+    oversized.push(metadata);
+    let mut metadata = token_metadata("item");
+    metadata.media = "m".repeat(513);
+    oversized.push(metadata);
+    let mut metadata = token_metadata("item");
+    metadata.reference = "r".repeat(513);
+    oversized.push(metadata);
+
+    for metadata in oversized {
+        let res = client
+            .mint(ADMIN_ID.into(), metadata.clone())
+            .send_recv(extended_vnft_id)
+            .await;
+        assert!(res.is_err());
+        let res = client
+            .mint_batch(ADMIN_ID.into(), vec![metadata])
+            .send_recv(extended_vnft_id)
+            .await;
+        assert!(res.is_err());
+    }
+
+    // exactly at the limits is fine
+    let mut metadata = token_metadata("item");
+    metadata.name = "n".repeat(128);
+    metadata.description = "d".repeat(1024);
+    client
+        .mint(ADMIN_ID.into(), metadata)
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_id_space_warning() {
+    let (program_space, extended_vnft_id) = deploy().await;
+    let mut client = VftClient::new(program_space.clone());
+    let mut listener = vnft_events::listener(program_space);
+    let mut events = listener.listen().await.unwrap();
+
+    client
+        .set_id_warning_threshold(Some(2.into()))
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+    for _ in 0..5 {
+        client
+            .mint(ADMIN_ID.into(), token_metadata("item"))
+            .send_recv(extended_vnft_id)
+            .await
+            .unwrap();
+    }
+
+    // warnings raised by the first four mints show up before the fifth Minted
+    let mut minted = 0;
+    let mut warnings = Vec::new();
+    while minted < 5 {
+        match events.next().await.unwrap().1 {
+            VnftEvents::Minted { .. } => minted += 1,
+            VnftEvents::IdSpaceWarning { remaining, .. } => warnings.push((minted, remaining)),
+            _ => {}
+        }
+    }
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].0, 2);
+}
+
+#[tokio::test]
+async fn test_rebuild_owner_index() {
+    let (program_space, extended_vnft_id) = deploy().await;
+    let mut client = VftClient::new(program_space);
+
+    client
+        .mint_batch(USER_ID[0].into(), vec![token_metadata("item"); 3])
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+    client
+        .transfer(USER_ID[1].into(), 1.into())
+        .with_args(|args| args.with_actor_id(USER_ID[0].into()))
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+
+    let res = client
+        .admin_rebuild_owner_index()
+        .with_args(|args| args.with_actor_id(USER_ID[0].into()))
+        .send_recv(extended_vnft_id)
+        .await;
+    assert!(res.is_err());
+    client
+        .admin_rebuild_owner_index()
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+
+    for (owner, expected) in [(USER_ID[0], vec![0, 2]), (USER_ID[1], vec![1])] {
+        let mut ids: Vec<_> = client
+            .tokens_for_owner(owner.into())
+            .recv(extended_vnft_id)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+        ids.sort();
+        let expected: Vec<_> = expected.into_iter().map(Into::into).collect();
+        assert_eq!(ids, expected);
+    }
+}
+
+#[tokio::test]
+async fn test_admin_set_locked() {
+    let (program_space, extended_vnft_id) = deploy().await;
+    let mut client = VftClient::new(program_space);
+
+    client
+        .mint_batch(USER_ID[0].into(), vec![token_metadata("item"); 3])
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+    // the missing id 9 is skipped
+    client
+        .admin_set_locked(vec![0.into(), 1.into(), 9.into()], true)
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+
+    for token_id in [0, 1] {
+        let res = client
+            .transfer(USER_ID[1].into(), token_id.into())
+            .with_args(|args| args.with_actor_id(USER_ID[0].into()))
+            .send_recv(extended_vnft_id)
+            .await;
+        assert!(res.is_err());
+    }
+    let res = client
+        .burn(USER_ID[0].into(), 0.into())
+        .send_recv(extended_vnft_id)
+        .await;
+    assert!(res.is_err());
+    client
+        .transfer(USER_ID[1].into(), 2.into())
+        .with_args(|args| args.with_actor_id(USER_ID[0].into()))
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+
+    client
+        .admin_set_locked(vec![0.into()], false)
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+    client
+        .transfer(USER_ID[1].into(), 0.into())
+        .with_args(|args| args.with_actor_id(USER_ID[0].into()))
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_mint_unique() {
+    let (program_space, extended_vnft_id) = deploy().await;
+    let mut client = VftClient::new(program_space);
+
+    client
+        .mint_unique(USER_ID[0].into(), token_metadata("wooden sword"), "starter".to_string())
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+    let res = client
+        .mint_unique(USER_ID[0].into(), token_metadata("rusty sword"), "starter".to_string())
+        .send_recv(extended_vnft_id)
+        .await;
+    assert!(res.is_err());
+
+    // other tags and other owners are unaffected
+    client
+        .mint_unique(USER_ID[0].into(), token_metadata("cap"), "starter_armor".to_string())
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+    client
+        .mint_unique(USER_ID[1].into(), token_metadata("rusty sword"), "starter".to_string())
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+    let tag = client.type_tag(0.into()).recv(extended_vnft_id).await.unwrap();
+    assert_eq!(tag, Some("starter".to_string()));
+}
+
+#[tokio::test]
+async fn test_retired_tokens() {
+    let (program_space, extended_vnft_id) = deploy().await;
+    let mut client = VftClient::new(program_space);
+
+    client
+        .mint_batch(USER_ID[0].into(), vec![token_metadata("item"); 3])
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+    client
+        .burn(USER_ID[0].into(), 2.into())
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+    client
+        .burn_batch(vec![0.into()])
+        .with_args(|args| args.with_actor_id(USER_ID[0].into()))
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+
+    let retired = client
+        .retired_tokens_of(USER_ID[0].into())
+        .recv(extended_vnft_id)
+        .await
+        .unwrap();
+    assert_eq!(retired, vec![2.into(), 0.into()]);
+}
+
+#[tokio::test]
+async fn test_emit_events_toggle() {
+    let (program_space, extended_vnft_id) = deploy().await;
+    let mut client = VftClient::new(program_space.clone());
+    let mut listener = vnft_events::listener(program_space);
+    let mut events = listener.listen().await.unwrap();
+
+    client
+        .set_emit_events(false)
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+    client
+        .mint(ADMIN_ID.into(), token_metadata("silent"))
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+    client
+        .set_emit_events(true)
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+    client
+        .mint(ADMIN_ID.into(), token_metadata("loud"))
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+
+    // both toggles are emitted, the muted mint only shows as a seq gap
+    let mut seen = Vec::new();
+    while !matches!(seen.last(), Some(VnftEvents::Minted { .. })) {
+        let event = events.next().await.unwrap().1;
+        if matches!(event, VnftEvents::EmitEventsSet(..) | VnftEvents::Minted { .. }) {
+            seen.push(event);
+        }
+    }
+    assert!(matches!(seen[0], VnftEvents::EmitEventsSet((false, 1))));
+    assert!(matches!(seen[1], VnftEvents::EmitEventsSet((true, 3))));
+    assert!(
+        matches!(seen[2], VnftEvents::Minted { token_id, seq: 4, .. } if token_id == 1.into())
+    );
+    let balance = client
+        .balance_of(ADMIN_ID.into())
+        .recv(extended_vnft_id)
+        .await
+        .unwrap();
+    assert_eq!(balance, 2.into());
+}
+
+#[tokio::test]
+async fn test_freeze_metadata() {
+    let (program_space, extended_vnft_id) = deploy().await;
+    let mut client = VftClient::new(program_space);
+
+    client
+        .mint(USER_ID[0].into(), token_metadata("heirloom"))
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+    // only the owner can freeze
+    let res = client
+        .freeze_metadata(0.into())
+        .send_recv(extended_vnft_id)
+        .await;
+    assert!(res.is_err());
+    client
+        .freeze_metadata(0.into())
+        .with_args(|args| args.with_actor_id(USER_ID[0].into()))
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+
+    let res = client
+        .set_description(0.into(), "changed".to_string())
+        .with_args(|args| args.with_actor_id(USER_ID[0].into()))
+        .send_recv(extended_vnft_id)
+        .await;
+    assert!(res.is_err());
+
+    client
+        .transfer(USER_ID[1].into(), 0.into())
+        .with_args(|args| args.with_actor_id(USER_ID[0].into()))
+        .send_recv(extended_vnft_id)
+        .await
+        .unwrap();
+    let metadata = client
+        .token_metadata_by_id(0.into())
+        .recv(extended_vnft_id)
+        .await
+        .unwrap();
+    assert_eq!(metadata, Some(token_metadata("heirloom")));
+}